@@ -0,0 +1,99 @@
+//! Historical stake balances.
+//!
+//! Every change to a stake records a checkpoint of the user's new balance and
+//! of the new `TotalStaked`, keyed by ledger sequence. Checkpoints are kept one
+//! per persistent entry next to a count, so a history can grow without bound
+//! while a lookup only reads the entries visited by a binary search.
+//!
+//! Balances from before a user's first checkpoint read as zero.
+//!
+//! Checkpoints are kept for `HISTORY_BUMP_AMOUNT` ledgers rather than the
+//! default month, and every lookup extends the entries it reads, so history
+//! that is still being queried is never archived.
+
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::storage;
+use crate::DataKey;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checkpoint {
+    /// Ledger sequence the balance was recorded in.
+    pub ledger: u32,
+    /// Balance at the end of that ledger, in stETH shares.
+    pub amount: i128,
+}
+
+fn read_checkpoint(env: &Env, key: &DataKey) -> Option<Checkpoint> {
+    let checkpoint = env.storage().persistent().get(key);
+    if checkpoint.is_some() {
+        storage::extend_history(env, key);
+    }
+    checkpoint
+}
+
+fn read_count(env: &Env, count_key: &DataKey) -> u32 {
+    let count = env.storage().persistent().get(count_key).unwrap_or(0);
+    if count > 0 {
+        storage::extend_history(env, count_key);
+    }
+    count
+}
+
+/// Appends a checkpoint to a history, replacing the last one if it was taken
+/// in the same ledger.
+fn push(env: &Env, count_key: DataKey, key: impl Fn(u32) -> DataKey, amount: i128) {
+    let ledger = env.ledger().sequence();
+    let mut count = read_count(env, &count_key);
+    let same_ledger = count > 0 && read_checkpoint(env, &key(count - 1)).is_some_and(|last| last.ledger == ledger);
+    if !same_ledger {
+        count += 1;
+        env.storage().persistent().set(&count_key, &count);
+    }
+    storage::extend_history(env, &count_key);
+    let entry = key(count - 1);
+    env.storage().persistent().set(&entry, &Checkpoint { ledger, amount });
+    storage::extend_history(env, &entry);
+}
+
+/// Returns the amount of the latest checkpoint taken at or before `ledger`.
+fn lookup(env: &Env, count_key: DataKey, key: impl Fn(u32) -> DataKey, ledger: u32) -> i128 {
+    let count = read_count(env, &count_key);
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = (low + high) / 2;
+        match read_checkpoint(env, &key(mid)) {
+            Some(checkpoint) if checkpoint.ledger <= ledger => low = mid + 1,
+            _ => high = mid,
+        }
+    }
+    if low == 0 {
+        return 0;
+    }
+    read_checkpoint(env, &key(low - 1)).map_or(0, |checkpoint| checkpoint.amount)
+}
+
+/// Records the user's new stake and the new total.
+pub(crate) fn write(env: &Env, user: &Address, amount: i128, total_staked: i128) {
+    push(
+        env,
+        DataKey::StakeCheckpointCount(user.clone()),
+        |index| DataKey::StakeCheckpoint(user.clone(), index),
+        amount,
+    );
+    push(env, DataKey::TotalStakedCheckpointCount, DataKey::TotalStakedCheckpoint, total_staked);
+}
+
+pub(crate) fn stake_at(env: &Env, user: &Address, ledger: u32) -> i128 {
+    lookup(
+        env,
+        DataKey::StakeCheckpointCount(user.clone()),
+        |index| DataKey::StakeCheckpoint(user.clone(), index),
+        ledger,
+    )
+}
+
+pub(crate) fn total_staked_at(env: &Env, ledger: u32) -> i128 {
+    lookup(env, DataKey::TotalStakedCheckpointCount, DataKey::TotalStakedCheckpoint, ledger)
+}
@@ -1,44 +1,552 @@
-use soroban_sdk::{contractclient, Address, Env};
+use soroban_sdk::{contractclient, Address, BytesN, Env, Vec};
 
+use crate::error::ChrysalisError;
+use crate::lock::{BoostCurve, Lock};
+use crate::reward_tokens::RewardPool;
+use crate::solvency::SolvencyReport;
+use crate::withdrawal::WithdrawalRequest;
 
-/// Interface for StakingContract
-#[contractclient(name = "ChrysalisClient")]
 
+/// Interface for StakingContract
+///
 /// Trait defining the interface for a Staking Contract.
+#[contractclient(name = "ChrysalisClient")]
 pub trait ChrysalisContractTrait {
 
     /*  *** Read-only functions: *** */
 
-    fn initialize_contract(env: Env, staked_token_address: Address, steth_address: Address);
+    /// Initializes the contract and records `admin` as its owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `admin` - The address that will administer the contract. Must authorize the call.
+    /// * `staked_token_address` - The token accepted for staking.
+    /// * `steth_address` - The stETH Stellar asset backing stakes. The contract must be
+    ///   its issuing admin, since stETH is minted on stake and burned on unstake.
+    ///   Staked shares are held by the contract.
+    fn initialize_contract(env: Env, admin: Address, staked_token_address: Address, steth_address: Address) -> Result<(), ChrysalisError>;
 
-    /// Returns the current staked amount for the user.
+    /// Returns the user's current stake, in stETH shares.
     fn get_stake_amount(e: Env, user: Address) -> i128;
 
-    /// Returns the current balance of stETH for the user.
+    /// Returns the sum of all stakes, in stETH shares. Shares escrowed in the
+    /// withdrawal queue are not included.
+    fn get_total_staked(env: Env) -> i128;
+
+    /// Returns the user's stake at the end of ledger `ledger`, in stETH shares.
+    ///
+    /// Stakes are only recorded from the first change made after checkpoints were
+    /// introduced; earlier ledgers read as zero.
+    fn get_stake_at(env: Env, user: Address, ledger: u32) -> i128;
+
+    /// Returns the sum of all stakes at the end of ledger `ledger`, in stETH shares.
+    fn get_total_staked_at(env: Env, ledger: u32) -> i128;
+
+    /// Returns the amount of staked token held for stETH holders (the TVL).
+    fn get_total_pooled(env: Env) -> i128;
+
+    /// Returns the stETH rewards paid out by `claim` or restaked by `compound` over
+    /// the lifetime of the contract.
+    fn get_total_rewards_paid(env: Env) -> i128;
+
+    /// Returns the number of users with a non-zero stake.
+    fn get_staker_count(env: Env) -> u32;
+
+    /// Returns the amount of staked token backing one stETH share, scaled by 1e9.
+    fn get_exchange_rate(env: Env) -> i128;
+
+    /// Returns the stETH shares `stake_eth` would mint for `amount` of the staked token.
+    fn preview_stake(env: Env, amount: i128) -> i128;
+
+    /// Returns the staked token `unstake_eth` would pay out for `shares` of stETH.
+    fn preview_unstake(env: Env, shares: i128) -> i128;
+
+    /// Returns the address the user delegated their voting power to, if any.
+    fn get_delegate(env: Env, user: Address) -> Option<Address>;
+
+    /// Returns the voting power delegated to `delegatee`: the sum of the power of
+    /// the locks of every user delegating to them.
+    fn get_delegated_power(env: Env, delegatee: Address) -> i128;
+
+    /// Returns the voting power delegated to `delegatee` at `timestamp`, which may
+    /// be in the past or, as a projection of current delegations, in the future.
+    fn get_delegated_power_at(env: Env, delegatee: Address, timestamp: u64) -> i128;
+
+    /// Returns whether `operator` currently holds every permission in `permissions`
+    /// over the user's position.
+    fn is_operator(env: Env, user: Address, operator: Address, permissions: u32) -> bool;
+
+    /// Returns the user's voting power, which comes from their locked shares and
+    /// decays linearly to zero as the locks approach their end. It is zero while
+    /// the user delegates to another address.
+    fn get_voting_power(env: Env, user: Address) -> i128;
+
+    /// Returns the user's voting power at `timestamp`, which may be in the past or
+    /// in the future. Future values assume the user's locks do not change.
+    fn get_voting_power_at(env: Env, user: Address, timestamp: u64) -> i128;
+
+    /// Returns the voting power of all users together.
+    fn get_total_voting_power(env: Env) -> i128;
+
+    /// Returns the user's locks, including expired ones that have not been dropped
+    /// by `kick` yet.
+    fn get_locks(env: Env, user: Address) -> Vec<Lock>;
+
+    /// Returns the treasury that receives early unlock penalties, if any.
+    fn get_treasury(env: Env) -> Option<Address>;
+
+    /// Returns the boost curve applied to new locks.
+    fn get_boost_curve(env: Env) -> BoostCurve;
+
+    /// Returns the user's pending withdrawal requests.
+    fn get_withdrawal_requests(env: Env, user: Address) -> Vec<WithdrawalRequest>;
+
+    /// Returns the unbonding period, in seconds, applied to new withdrawal requests.
+    fn get_unbonding_period(env: Env) -> u64;
+
+    /// Returns the guardian allowed to pause the contract, if any.
+    fn get_guardian(env: Env) -> Option<Address>;
+
+    /// Returns the currently paused operations as a bit set of `PAUSE_*` flags.
+    fn get_paused(env: Env) -> u32;
+
+    /// Compares what the contract owes in the staked token and in stETH against
+    /// the balances it actually holds.
+    fn check_solvency(env: Env) -> Result<SolvencyReport, ChrysalisError>;
+
+    // Returns the current balance of stETH for the user.
     // fn get_steth_balance(e: Env, user: Address) -> i64;
 
+    /// Returns the current admin of the contract.
+    fn get_admin(env: Env) -> Result<Address, ChrysalisError>;
+
+    /// Returns the storage schema version the contract data is in.
+    fn version(env: Env) -> u32;
+
+    /// Returns the number of legacy stake records still to migrate.
+    fn get_legacy_remaining(env: Env) -> u32;
+
+    /// Returns the amount of stETH emitted per second, shared pro rata between all stakers.
+    fn get_reward_rate(env: Env) -> i128;
+
+    /// Returns whether keepers may compound the user's rewards.
+    fn get_auto_compound(env: Env, user: Address) -> bool;
+
+    /// Returns the funded rewards that have not been emitted yet.
+    fn get_reward_budget_remaining(env: Env) -> i128;
+
+    /// Returns the timestamp at which reward emission stops.
+    fn get_period_finish(env: Env) -> u64;
+
+    /// Returns every registered reward token besides stETH, including removed ones.
+    fn get_reward_tokens(env: Env) -> Vec<Address>;
+
+    /// Returns the emission state of a registered reward token.
+    fn get_reward_pool(env: Env, token: Address) -> Result<RewardPool, ChrysalisError>;
+
+    /// Returns the user's unclaimed rewards in `token`, which may be stETH or any
+    /// registered reward token.
+    fn pending_reward(env: Env, user: Address, token: Address) -> Result<i128, ChrysalisError>;
+
+    /*  *** Admin Functions: *** */
+
+    /// Proposes `new_admin` as the next admin. Only the current admin may call this.
+    ///
+    /// The transfer completes once `new_admin` calls `accept_admin`, so a mistyped
+    /// address never takes control of the contract.
+    fn propose_admin(env: Env, new_admin: Address) -> Result<(), ChrysalisError>;
+
+    /// Completes a pending admin transfer. Must be authorized by the proposed admin.
+    fn accept_admin(env: Env) -> Result<(), ChrysalisError>;
+
+    /// Replaces the contract code in place. Only the admin may call this.
+    ///
+    /// The new code takes effect once the current invocation finishes; call
+    /// `begin_migration` and `migrate` afterwards if it introduces a new storage schema.
+    fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> Result<(), ChrysalisError>;
+
+    /// Starts moving legacy stake records to the current storage schema. Only the
+    /// admin may call this, and only while the schema is out of date.
+    ///
+    /// `legacy_count` is the number of legacy records, gathered off-chain. Calling
+    /// this again replaces the count of records still to migrate.
+    ///
+    /// A contract deployed from the original schema 1 code cannot be upgraded and
+    /// has no admin, so it cannot be migrated in place; see the `migration` module.
+    fn begin_migration(env: Env, legacy_count: u32) -> Result<(), ChrysalisError>;
+
+    /// Moves the stake records of `users` from an older storage schema to the current
+    /// one. Only the admin may call this.
+    ///
+    /// Users without a legacy record are skipped, so batches can be retried safely.
+    /// The schema version is bumped by the batch that migrates the last legacy
+    /// record. Returns the number of records migrated. Like `begin_migration`, this
+    /// does not apply to contracts deployed from the original schema 1 code.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `users` - The users to migrate, at most 25 per call.
+    fn migrate(env: Env, users: Vec<Address>) -> Result<u32, ChrysalisError>;
+
+    /// Checks solvency and, if the contract is under-collateralized, publishes a
+    /// deficit event and pauses all operations. Only the admin may call this.
+    ///
+    /// Returns the report the decision was based on.
+    fn reconcile(env: Env) -> Result<SolvencyReport, ChrysalisError>;
+
+    /// Sets the reward emission rate. Only the admin may call this.
+    ///
+    /// Rewards accrued so far are settled at the old rate, so the change only
+    /// applies going forward. The remaining budget is spread at the new rate,
+    /// which moves the end of the emission period accordingly.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `rate` - The amount of stETH emitted per second. Must not be negative.
+    fn set_reward_rate(env: Env, rate: i128) -> Result<(), ChrysalisError>;
+
+    /// Funds stETH rewards to be emitted evenly over `duration`. Only the admin may
+    /// call this.
+    ///
+    /// The part of the previous budget that has not been emitted yet is rolled
+    /// into the new period, and the reward rate becomes the total budget divided
+    /// by `duration`. Emission stops once the period ends.
+    ///
+    /// Reward stETH is obtained by staking. Whatever the funder does not hold
+    /// outside their stake is taken out of it, so it stops earning for them.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `from` - The address paying the rewards in stETH. Must authorize the call.
+    /// * `amount` - The amount of stETH to add to the budget.
+    /// * `duration` - The length of the new emission period, in seconds.
+    fn fund_rewards(env: Env, from: Address, amount: i128, duration: u64) -> Result<(), ChrysalisError>;
+
+    /// Registers `token` as an additional reward token, or resumes a removed one.
+    /// Only the admin may call this.
+    ///
+    /// Nothing is emitted until the token is funded with `fund_reward_token`.
+    fn add_reward_token(env: Env, token: Address) -> Result<(), ChrysalisError>;
+
+    /// Stops emitting `token` and refunds its unspent budget to the admin. Only the
+    /// admin may call this.
+    ///
+    /// Rewards already earned in the token remain claimable.
+    fn remove_reward_token(env: Env, token: Address) -> Result<(), ChrysalisError>;
+
+    /// Funds a registered reward token to be emitted evenly over `duration`, the
+    /// same way `fund_rewards` funds stETH. Only the admin may call this.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `from` - The address paying the rewards. Must authorize the call.
+    /// * `token` - The registered reward token.
+    /// * `amount` - The amount of `token` to add to the budget.
+    /// * `duration` - The length of the new emission period, in seconds.
+    fn fund_reward_token(env: Env, from: Address, token: Address, amount: i128, duration: u64) -> Result<(), ChrysalisError>;
+
+    /// Sets the boost curve applied to new and extended locks. Only the admin may call this.
+    ///
+    /// The boost grows linearly from 1x for no lock to `max_boost` for a lock of
+    /// `max_lock_duration`. Existing locks keep the boost they were made with.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `max_boost` - The boost of the longest lock, in basis points. Must be at least 10000.
+    /// * `max_lock_duration` - The longest lock accepted, in seconds.
+    fn set_boost_curve(env: Env, max_boost: u32, max_lock_duration: u64) -> Result<(), ChrysalisError>;
+
+    /// Sets the treasury that receives early unlock penalties as stake, or clears it
+    /// so that penalties are added to the reward budget. Only the admin may call this.
+    fn set_treasury(env: Env, treasury: Option<Address>) -> Result<(), ChrysalisError>;
+
+    /// Sets the guardian, who may pause and unpause the contract alongside the admin.
+    /// Only the admin may call this.
+    fn set_guardian(env: Env, guardian: Address) -> Result<(), ChrysalisError>;
+
+    /// Pauses the operations selected by `flags` (`PAUSE_DEPOSITS`, `PAUSE_WITHDRAWALS`,
+    /// `PAUSE_CLAIMS`). Gated entrypoints fail with `ChrysalisError::Paused`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `caller` - The admin or the guardian. Must authorize the call.
+    /// * `flags` - The operations to pause, added to those already paused.
+    fn pause(env: Env, caller: Address, flags: u32) -> Result<(), ChrysalisError>;
+
+    /// Resumes the operations selected by `flags`. Restricted to the admin or the guardian.
+    fn unpause(env: Env, caller: Address, flags: u32) -> Result<(), ChrysalisError>;
+
+    /// Adds yield to the pool, raising the value of every stETH share. Only the admin may call this.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `from` - The address paying the yield in the staked token. Must authorize the call.
+    /// * `amount` - The amount of the staked token to add to the pool.
+    fn distribute_yield(env: Env, from: Address, amount: i128) -> Result<(), ChrysalisError>;
+
+    /// Sets the unbonding period applied to new withdrawal requests. Only the admin may call this.
+    ///
+    /// While the period is non-zero, `unstake_eth` is disabled and stakers leave
+    /// through `request_unstake` and `claim_withdrawal`. Existing requests keep the
+    /// unlock time they were created with.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `period` - The unbonding period in seconds.
+    fn set_unbonding_period(env: Env, period: u64) -> Result<(), ChrysalisError>;
+
+    /// Restores the principal of a stake that earlier versions reduced on every `claim`.
+    /// Only the admin may call this.
+    ///
+    /// The corrected principal is reconstructed off-chain from the user's stake and
+    /// unstake history. It can only raise the recorded stake, and the restored shares
+    /// must be stETH the contract holds but owes to nobody. Contracts deployed from
+    /// the original code, which reduced stakes this way, have no admin and cannot
+    /// be upgraded, so this only corrects records of an instance of this code.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The address whose stake is corrected.
+    /// * `principal` - The corrected stake, in stETH shares.
+    fn restore_principal(env: Env, user: Address, principal: i128) -> Result<(), ChrysalisError>;
+
     /*  *** State-Changing Functions: *** */
 
-    /// Stakes a specified amount of the custom token, mints stETH, and locks it for the user.
+    /// Stakes a specified amount of the custom token and mints stETH shares at the current exchange rate.
+    /// The contract holds the minted shares for the user's stake.
     /// 
     /// # Arguments
     /// 
     /// * `e` - An instance of the `Env` struct.
     /// * `user` - The address of the user staking the tokens.
     /// * `amount` - The amount of the custom token to stake.
-    fn stake_eth(e: Env, user: Address, amount: i128);
+    fn stake_eth(e: Env, user: Address, amount: i128) -> Result<(), ChrysalisError>;
+
+    /// Stakes on behalf of `beneficiary`: `payer` pays the staked token, while the
+    /// stake, backed by the minted stETH, is credited to `beneficiary`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `payer` - The address paying the staked token. Must authorize the call.
+    /// * `beneficiary` - The address credited with the stake.
+    /// * `amount` - The amount of the custom token to stake.
+    fn stake_for(env: Env, payer: Address, beneficiary: Address, amount: i128) -> Result<(), ChrysalisError>;
+
+    /// Stakes stETH the user holds, such as claimed rewards or stETH issued before
+    /// staked shares were held by the contract. The shares move into the contract's
+    /// custody and are added to the user's stake.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The address staking their stETH. Must authorize the call.
+    /// * `shares` - The amount of stETH shares to stake.
+    fn stake_steth(env: Env, user: Address, shares: i128) -> Result<(), ChrysalisError>;
+
+    /// Stakes like `stake_eth`, but locks the minted shares until `lock_duration` has
+    /// passed in exchange for a reward boost set by the boost curve.
+    ///
+    /// Returns the id of the new lock.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The address of the user staking the tokens.
+    /// * `amount` - The amount of the custom token to stake.
+    /// * `lock_duration` - How long the shares stay locked, in seconds.
+    fn stake_locked(env: Env, user: Address, amount: i128, lock_duration: u64) -> Result<u64, ChrysalisError>;
+
+    /// Moves the unlock time of one of the user's locks to `lock_duration` from now.
+    /// The boost is recomputed for the new duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The owner of the lock.
+    /// * `lock_id` - The lock to extend.
+    /// * `lock_duration` - The new lock duration, in seconds. Must end after the current unlock time.
+    fn extend_lock(env: Env, user: Address, lock_id: u64, lock_duration: u64) -> Result<(), ChrysalisError>;
+
+    /// Drops the user's expired locks, so that they no longer boost the user's weight
+    /// or `TotalWeight`. Anyone may call this. Returns the number of locks dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The owner of the locks.
+    fn kick(env: Env, user: Address) -> u32;
+
+    /// Releases one of the user's locks before its unlock time.
+    ///
+    /// A penalty of up to half the locked shares is taken, shrinking linearly with
+    /// the time remaining. If a treasury is set the penalty becomes its stake, and
+    /// otherwise the forfeited shares are added to the reward budget, to be emitted
+    /// to stakers over the rest of the current period or the next funded one. The
+    /// rest of the shares become freely withdrawable.
+    /// Returns the penalty, in stETH shares.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The owner of the lock.
+    /// * `lock_id` - The lock to release.
+    fn early_unlock(env: Env, user: Address, lock_id: u64) -> Result<i128, ChrysalisError>;
 
-    /// Unstakes a specified amount using stETH, burns the stETH, and returns the original tokens.
+    /// Burns a specified amount of stETH shares and returns their proportional part of the pool.
+    ///
+    /// stETH the user holds outside their stake, such as claimed rewards, is
+    /// redeemed first; only the rest comes out of the stake. An operator only
+    /// redeems from the stake. Shares that are still locked cannot be unstaked.
     /// 
     /// # Arguments
     /// 
     /// * `e` - An instance of the `Env` struct.
-    /// * `user` - The address of the user unstaking the tokens.
-    /// * `amount` - The amount of stETH to use for unstaking.
-    fn unstake_eth(e: Env, user: Address, amount: i128);
+    /// * `caller` - The user, or an operator holding `OPERATOR_UNSTAKE` that unstakes
+    ///   within its unstake allowance. Must authorize the call.
+    /// * `user` - The address of the user unstaking the tokens, who is paid the staked token.
+    /// * `amount` - The amount of stETH shares to redeem.
+    fn unstake_eth(e: Env, caller: Address, user: Address, amount: i128) -> Result<(), ChrysalisError>;
+
+    /// Moves `amount` of the sender's stake to `to`. The stETH backing it stays in
+    /// the contract's custody.
+    ///
+    /// Both positions are settled first, and the moved shares take a proportional
+    /// part of the sender's unclaimed rewards with them. If `to` already has a
+    /// stake the two are merged, its age becoming the amount-weighted average of
+    /// both. Locked shares cannot be moved; the sender's locks stay with them.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `from` - The sender. Must authorize the call.
+    /// * `to` - The address receiving the stake.
+    /// * `amount` - The amount of stETH shares to move.
+    fn transfer_stake(env: Env, from: Address, to: Address, amount: i128) -> Result<(), ChrysalisError>;
+
+    /// Starts a withdrawal: escrows stETH shares in the contract until the unbonding period ends.
+    ///
+    /// The shares stop earning rewards immediately. As with `unstake_eth`, stETH held
+    /// outside the stake is escrowed first. Returns the id of the new request.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The address of the user withdrawing.
+    /// * `amount` - The amount of stETH shares to withdraw.
+    fn request_unstake(env: Env, user: Address, amount: i128) -> Result<u64, ChrysalisError>;
+
+    /// Completes a matured withdrawal request, burning its shares and paying out the
+    /// staked token at the current exchange rate. Returns the amount paid out.
+    fn claim_withdrawal(env: Env, user: Address, request_id: u64) -> Result<i128, ChrysalisError>;
+
+    /// Cancels a pending withdrawal request. Shares that came out of the user's stake
+    /// return to it; the rest are handed back to the user.
+    fn cancel_withdrawal(env: Env, user: Address, request_id: u64) -> Result<(), ChrysalisError>;
 
-    fn claim(env: Env, user: Address , apy: i128)-> i128;
+    /// Extends the TTL of the user's stake record so it is not archived. The user's
+    /// locks and the voting power entries they feed are kept until the locks end.
+    ///
+    /// Anyone may call this; it only keeps an existing position alive and drops its
+    /// expired locks.
+    fn bump_stake(env: Env, user: Address) -> Result<(), ChrysalisError>;
 
-    fn vclaim(env: Env, user: Address , apy: i128)-> i128;
+    /// Pays out the user's accrued rewards in stETH from the reward budget.
+    ///
+    /// The staked principal is not affected. `caller` is the user or an operator
+    /// holding `OPERATOR_CLAIM`, and must authorize the call; the rewards always go
+    /// to the user.
+    fn claim(env: Env, caller: Address, user: Address) -> Result<i128, ChrysalisError>;
+
+    /// Pays out the user's rewards in stETH and in every registered reward token.
+    ///
+    /// Returns the amount paid per token, leaving out tokens with nothing to pay.
+    fn claim_all(env: Env, user: Address) -> Result<Vec<(Address, i128)>, ChrysalisError>;
+
+    /// Returns the rewards `claim` would pay the user right now.
+    fn vclaim(env: Env, user: Address) -> i128;
+
+    /// Delegates the user's voting power to `delegatee`, replacing any previous
+    /// delegate. The delegated power decays with the user's locks and follows
+    /// every later change to them. The power moves rather than being copied: the
+    /// user's own `get_voting_power` is zero while they delegate to another address.
+    ///
+    /// Users who want to vote with their own power delegate to themselves.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The staker. Must authorize the call.
+    /// * `delegatee` - The address receiving the power.
+    fn delegate(env: Env, user: Address, delegatee: Address) -> Result<(), ChrysalisError>;
+
+    /// Lets `operator` manage the user's position, replacing any previous approval
+    /// of the same operator.
+    ///
+    /// With `OPERATOR_UNSTAKE`, the operator may redeem up to `unstake_allowance` of
+    /// the user's stake, never stETH the user holds outside it.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The staker. Must authorize the call.
+    /// * `operator` - The address being approved.
+    /// * `permissions` - Bit set of `OPERATOR_CLAIM`, `OPERATOR_COMPOUND` and `OPERATOR_UNSTAKE`.
+    /// * `expiration_ledger` - Last ledger sequence in which the approval can be used.
+    /// * `unstake_allowance` - Staked shares the operator may unstake; zero unless
+    ///   `OPERATOR_UNSTAKE` is granted.
+    fn approve_operator(
+        env: Env,
+        user: Address,
+        operator: Address,
+        permissions: u32,
+        expiration_ledger: u32,
+        unstake_allowance: i128,
+    ) -> Result<(), ChrysalisError>;
+
+    /// Withdraws every permission the user granted to `operator`, along with what
+    /// is left of its unstake allowance.
+    fn revoke_operator(env: Env, user: Address, operator: Address) -> Result<(), ChrysalisError>;
+
+    /// Restakes the user's stETH rewards instead of paying them out.
+    ///
+    /// The reward shares are redeemed and deposited again at the current exchange
+    /// rate, as if they had been claimed and staked, and the stETH minted for them
+    /// is added to the user's stake, held by the contract. Returns the shares added
+    /// to the stake.
+    ///
+    /// `caller` is the user or an operator holding `OPERATOR_COMPOUND`, and must
+    /// authorize the call.
+    fn compound(env: Env, caller: Address, user: Address) -> Result<i128, ChrysalisError>;
+
+    /// Opts the user in or out of having their rewards compounded by `compound_batch`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `user` - The staker. Must authorize the call.
+    /// * `enabled` - Whether keepers may compound the user's rewards.
+    fn set_auto_compound(env: Env, user: Address, enabled: bool) -> Result<(), ChrysalisError>;
+
+    /// Compounds the rewards of every user in `users` who opted in through
+    /// `set_auto_compound`; others are skipped. Anyone may call this.
+    ///
+    /// Returns the number of users whose rewards were compounded.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - An instance of the `Env` struct.
+    /// * `users` - The users to compound, at most 25 per call.
+    fn compound_batch(env: Env, users: Vec<Address>) -> Result<u32, ChrysalisError>;
     
 }
@@ -0,0 +1,86 @@
+//! Delegation of voting power.
+//!
+//! A staker keeps their position but may name a delegate who votes with it.
+//! What is delegated is the vote-escrowed power of the staker's locks, so a
+//! delegate's power is the sum of that power for everyone delegating to them:
+//! it decays with their locks and follows every change to them. The history is
+//! kept by the `ve` module. Power that was never delegated does not count
+//! towards anyone's; stakers who want to vote themselves delegate to their own
+//! address.
+//!
+//! Delegating moves the power rather than copying it: while a staker delegates
+//! to another address, their own `get_voting_power` is zero.
+
+use soroban_sdk::{Address, Env, Vec};
+
+use crate::lock::{self, Lock};
+use crate::storage;
+use crate::{events, ve, DataKey};
+
+pub(crate) fn read_delegate(env: &Env, user: &Address) -> Option<Address> {
+    env.storage().persistent().get(&DataKey::Delegate(user.clone()))
+}
+
+pub(crate) fn read_power(env: &Env, delegatee: &Address) -> i128 {
+    ve::delegated_power_at(env, delegatee, env.ledger().timestamp())
+}
+
+fn move_locks(env: &Env, delegatee: &Address, old_locks: &Vec<Lock>, new_locks: &Vec<Lock>) {
+    let old_power = read_power(env, delegatee);
+    ve::checkpoint_delegated(env, delegatee, old_locks, new_locks);
+    let new_power = read_power(env, delegatee);
+    if new_power != old_power {
+        events::delegated_power_changed(env, delegatee, old_power, new_power);
+    }
+}
+
+/// Whether the user's locks count towards their own voting power, which they do
+/// unless they are delegated to another address.
+fn counts_for_user(user: &Address, delegatee: &Option<Address>) -> bool {
+    delegatee.as_ref().is_none_or(|delegatee| delegatee == user)
+}
+
+/// Points the voting power of the user's locks at `delegatee`.
+pub(crate) fn set_delegate(env: &Env, user: &Address, delegatee: &Address) {
+    let locks = lock::read_locks(env, user);
+    let no_locks = Vec::new(env);
+    let previous = read_delegate(env, user);
+    if let Some(previous) = &previous {
+        move_locks(env, previous, &locks, &no_locks);
+    }
+    move_locks(env, delegatee, &no_locks, &locks);
+
+    // The power leaves the user's own while it is delegated to someone else
+    match (counts_for_user(user, &previous), counts_for_user(user, &Some(delegatee.clone()))) {
+        (true, false) => ve::checkpoint_user(env, user, &locks, &no_locks),
+        (false, true) => ve::checkpoint_user(env, user, &no_locks, &locks),
+        _ => {}
+    }
+
+    let key = DataKey::Delegate(user.clone());
+    env.storage().persistent().set(&key, delegatee);
+    storage::extend_persistent(env, &key);
+    events::delegate_changed(env, user, previous, delegatee);
+}
+
+/// Moves the power of the user and of their delegate along with a change of
+/// the user's locks.
+pub(crate) fn on_locks_changed(env: &Env, user: &Address, old_locks: &Vec<Lock>, new_locks: &Vec<Lock>) {
+    let delegatee = read_delegate(env, user);
+    if let Some(delegatee) = &delegatee {
+        move_locks(env, delegatee, old_locks, new_locks);
+        storage::extend_persistent(env, &DataKey::Delegate(user.clone()));
+    }
+    if counts_for_user(user, &delegatee) {
+        ve::checkpoint_user(env, user, old_locks, new_locks);
+    }
+}
+
+/// Extends the delegation entries the user's locks are counted in.
+pub(crate) fn bump(env: &Env, user: &Address, locks: &Vec<Lock>) {
+    let key = DataKey::Delegate(user.clone());
+    if let Some(delegatee) = read_delegate(env, user) {
+        storage::extend_persistent_until(env, &key, lock::last_unlock_time(locks));
+        ve::bump_delegated(env, &delegatee, locks);
+    }
+}
@@ -0,0 +1,65 @@
+use soroban_sdk::contracterror;
+
+/// Errors returned by the Chrysalis contract.
+///
+/// The discriminants are part of the public interface: clients calling the
+/// `try_` variants receive them as `Error(Contract, #code)`, so existing codes
+/// must never be renumbered.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum ChrysalisError {
+    /// `initialize_contract` has already been called.
+    AlreadyInitialized = 1,
+    /// The contract has not been initialized yet.
+    NotInitialized = 2,
+    /// The user does not hold enough of the token being deposited.
+    InsufficientBalance = 3,
+    /// The user's stake is smaller than the amount requested.
+    InsufficientStake = 4,
+    /// Amounts and rates must be positive (or non-negative where zero is meaningful).
+    InvalidAmount = 5,
+    /// The caller is not allowed to perform the action.
+    Unauthorized = 6,
+    /// `accept_admin` was called without a pending proposal.
+    NoPendingAdmin = 7,
+    /// There is no stake to act on: the user has no record, or nobody is staked.
+    NoStake = 8,
+    /// Withdrawals must go through `request_unstake` while an unbonding period is set.
+    UnbondingRequired = 9,
+    /// No withdrawal request exists with the given id.
+    WithdrawalNotFound = 10,
+    /// The withdrawal request has not reached its unlock time yet.
+    WithdrawalNotReady = 11,
+    /// The user already has the maximum number of pending withdrawal requests.
+    TooManyWithdrawals = 12,
+    /// The requested operation is paused.
+    Paused = 13,
+    /// Too many entries were passed to a batched call.
+    BatchTooLarge = 14,
+    /// The token is already registered as a reward token.
+    RewardTokenExists = 15,
+    /// The token is not a registered reward token, or it has been removed.
+    RewardTokenNotFound = 16,
+    /// The maximum number of reward tokens has been registered.
+    TooManyRewardTokens = 17,
+    /// Part of the requested amount is still locked.
+    StakeLocked = 18,
+    /// The user has no lock with the given id.
+    LockNotFound = 19,
+    /// The lock duration is zero, longer than the boost curve allows, or does
+    /// not extend the lock.
+    InvalidLockDuration = 20,
+    /// The user already has the maximum number of active locks.
+    TooManyLocks = 21,
+    /// The expiration ledger of an approval is already in the past.
+    InvalidExpirationLedger = 22,
+    /// Legacy stakes cannot be migrated before `begin_migration` is called.
+    MigrationNotStarted = 23,
+    /// The storage schema is already at the current version.
+    AlreadyMigrated = 24,
+    /// The contract's internal accounting is inconsistent.
+    InvalidAccounting = 25,
+    /// The amount is more than the operator is still allowed to unstake.
+    AllowanceExceeded = 26,
+}
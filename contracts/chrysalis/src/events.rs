@@ -0,0 +1,277 @@
+//! Events published by the contract.
+//!
+//! Indexers can rebuild every position from these events alone. Each event is
+//! published with a topic tuple whose first element names the event, and a data
+//! tuple whose first element is the schema version ([`EVENT_VERSION`]). Fields
+//! are only ever appended to a data tuple; any other change bumps the version.
+//!
+//! Schema version 1:
+//!
+//! | Event      | Topics                    | Data                                                       |
+//! |------------|---------------------------|------------------------------------------------------------|
+//! | init       | `("init",)`               | `(version, admin, staked_token, steth, timestamp)`         |
+//! | stake      | `("stake", user)`         | `(version, amount, new_stake_balance, timestamp, shares, payer)` |
+//! | unstake    | `("unstake", user)`       | `(version, amount, new_stake_balance, timestamp, shares)`  |
+//! | transfer   | `("transfer", from, to)`  | `(version, shares, rewards, new_from_balance, new_to_balance, timestamp)` |
+//! | claim      | `("claim", user)`         | `(version, rewards, new_stake_balance, timestamp)`         |
+//! | reward rate| `("rate",)`               | `(version, old_rate, new_rate, timestamp)`                 |
+//! | admin      | `("adm_prop", new_admin)` | `(version, current_admin, timestamp)`                      |
+//! | admin      | `("adm_acc", new_admin)`  | `(version, previous_admin, timestamp)`                     |
+//! | yield      | `("yield", from)`         | `(version, amount, total_pooled, timestamp)`               |
+//! | restore    | `("restore", user)`       | `(version, restored, new_stake_balance, timestamp)`        |
+//! | withdrawal | `("wd_req", user)`        | `(version, request_id, shares, unlock_time, new_stake_balance, timestamp)` |
+//! | withdrawal | `("wd_claim", user)`      | `(version, request_id, shares, amount, timestamp)`         |
+//! | withdrawal | `("wd_cancel", user)`     | `(version, request_id, shares, new_stake_balance, timestamp)` |
+//! | pause      | `("pause", caller)`       | `(version, paused_flags, timestamp)`                       |
+//! | upgrade    | `("upgrade",)`            | `(version, new_wasm_hash, timestamp)`                      |
+//! | migrate    | `("migrate",)`            | `(version, records_migrated, schema_version, timestamp)`   |
+//! | lock       | `("lock", user)`          | `(version, lock_id, shares, unlock_time, boost, timestamp)` |
+//! | unlock     | `("unlock", user)`        | `(version, lock_id, released, penalty, new_stake_balance, timestamp)` |
+//! | delegate   | `("delegate", user)`      | `(version, previous_delegate, new_delegate, timestamp)`    |
+//! | delegate   | `("dlg_power", delegatee)`| `(version, old_power, new_power, timestamp)`               |
+//! | operator   | `("operator", user, operator)` | `(version, permissions, expiration_ledger, timestamp)` |
+//! | compound   | `("compound", user)`      | `(version, rewards, new_stake_balance, timestamp)`         |
+//! | fund       | `("fund", from)`          | `(version, amount, reward_rate, period_finish, timestamp, token, new_stake_balance)` |
+//! | reward     | `("rwd_add", token)`      | `(version, timestamp)`                                     |
+//! | reward     | `("rwd_rm", token)`       | `(version, refunded_budget, timestamp)`                    |
+//! | reward     | `("rwd_claim", user)`     | `(version, token, amount, timestamp)`                      |
+//! | deficit    | `("deficit",)`            | `(version, staked_deficit, steth_deficit, timestamp)`      |
+//!
+//! `payer` on stake events is the address that paid the staked token, which is
+//! the staker unless the stake was made through `stake_for`.
+//! `previous_delegate` is `None` for a user's first delegation.
+//! `rewards` on transfer events is the unclaimed stETH moved along with the shares.
+//! `operator` is published with zero permissions when an operator is revoked.
+//! `lock` is published both when a lock is created and when it is extended.
+//! `unlock` is also published, with a zero penalty, when an expired lock is dropped.
+//! `fund` is also published, from the unlocking user, when an early unlock penalty
+//! is added to the reward budget.
+//! `new_stake_balance` on fund events is the funder's stake once the funded stETH has
+//! left it.
+//! `amount` on stake and unstake events is denominated in the staked token,
+//! while `new_stake_balance` and `shares` are denominated in stETH shares.
+
+use soroban_sdk::{symbol_short, Address, BytesN, Env};
+
+use crate::lock::Lock;
+
+/// Version of the event schema documented above.
+pub const EVENT_VERSION: u32 = 1;
+
+pub(crate) fn initialized(env: &Env, admin: &Address, staked_token: &Address, steth: &Address) {
+    env.events().publish(
+        (symbol_short!("init"),),
+        (EVENT_VERSION, admin.clone(), staked_token.clone(), steth.clone(), env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn staked(env: &Env, user: &Address, amount: i128, new_balance: i128, shares: i128, payer: &Address) {
+    env.events().publish(
+        (symbol_short!("stake"), user.clone()),
+        (EVENT_VERSION, amount, new_balance, env.ledger().timestamp(), shares, payer.clone()),
+    );
+}
+
+pub(crate) fn unstaked(env: &Env, user: &Address, amount: i128, new_balance: i128, shares: i128) {
+    env.events().publish(
+        (symbol_short!("unstake"), user.clone()),
+        (EVENT_VERSION, amount, new_balance, env.ledger().timestamp(), shares),
+    );
+}
+
+pub(crate) fn stake_transferred(
+    env: &Env,
+    from: &Address,
+    to: &Address,
+    shares: i128,
+    rewards: i128,
+    new_from_balance: i128,
+    new_to_balance: i128,
+) {
+    env.events().publish(
+        (symbol_short!("transfer"), from.clone(), to.clone()),
+        (EVENT_VERSION, shares, rewards, new_from_balance, new_to_balance, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn claimed(env: &Env, user: &Address, rewards: i128, new_balance: i128) {
+    env.events().publish(
+        (symbol_short!("claim"), user.clone()),
+        (EVENT_VERSION, rewards, new_balance, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn locked(env: &Env, user: &Address, lock: &Lock) {
+    env.events().publish(
+        (symbol_short!("lock"), user.clone()),
+        (EVENT_VERSION, lock.id, lock.amount, lock.unlock_time, lock.boost, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn unlocked(env: &Env, user: &Address, lock_id: u64, released: i128, penalty: i128, new_balance: i128) {
+    env.events().publish(
+        (symbol_short!("unlock"), user.clone()),
+        (EVENT_VERSION, lock_id, released, penalty, new_balance, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn delegate_changed(env: &Env, user: &Address, previous: Option<Address>, delegatee: &Address) {
+    env.events().publish(
+        (symbol_short!("delegate"), user.clone()),
+        (EVENT_VERSION, previous, delegatee.clone(), env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn delegated_power_changed(env: &Env, delegatee: &Address, old_power: i128, new_power: i128) {
+    env.events().publish(
+        (symbol_short!("dlg_power"), delegatee.clone()),
+        (EVENT_VERSION, old_power, new_power, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn operator_changed(
+    env: &Env,
+    user: &Address,
+    operator: &Address,
+    permissions: u32,
+    expiration_ledger: u32,
+) {
+    env.events().publish(
+        (symbol_short!("operator"), user.clone(), operator.clone()),
+        (EVENT_VERSION, permissions, expiration_ledger, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn compounded(env: &Env, user: &Address, rewards: i128, new_balance: i128) {
+    env.events().publish(
+        (symbol_short!("compound"), user.clone()),
+        (EVENT_VERSION, rewards, new_balance, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn reward_rate_set(env: &Env, old_rate: i128, new_rate: i128) {
+    env.events().publish(
+        (symbol_short!("rate"),),
+        (EVENT_VERSION, old_rate, new_rate, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn admin_proposed(env: &Env, current_admin: &Address, new_admin: &Address) {
+    env.events().publish(
+        (symbol_short!("adm_prop"), new_admin.clone()),
+        (EVENT_VERSION, current_admin.clone(), env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn admin_accepted(env: &Env, previous_admin: &Address, new_admin: &Address) {
+    env.events().publish(
+        (symbol_short!("adm_acc"), new_admin.clone()),
+        (EVENT_VERSION, previous_admin.clone(), env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn yield_distributed(env: &Env, from: &Address, amount: i128, total_pooled: i128) {
+    env.events().publish(
+        (symbol_short!("yield"), from.clone()),
+        (EVENT_VERSION, amount, total_pooled, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn principal_restored(env: &Env, user: &Address, restored: i128, new_balance: i128) {
+    env.events().publish(
+        (symbol_short!("restore"), user.clone()),
+        (EVENT_VERSION, restored, new_balance, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn withdrawal_requested(
+    env: &Env,
+    user: &Address,
+    request_id: u64,
+    shares: i128,
+    unlock_time: u64,
+    new_balance: i128,
+) {
+    env.events().publish(
+        (symbol_short!("wd_req"), user.clone()),
+        (EVENT_VERSION, request_id, shares, unlock_time, new_balance, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn withdrawal_claimed(env: &Env, user: &Address, request_id: u64, shares: i128, amount: i128) {
+    env.events().publish(
+        (symbol_short!("wd_claim"), user.clone()),
+        (EVENT_VERSION, request_id, shares, amount, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn withdrawal_cancelled(env: &Env, user: &Address, request_id: u64, shares: i128, new_balance: i128) {
+    env.events().publish(
+        (symbol_short!("wd_cancel"), user.clone()),
+        (EVENT_VERSION, request_id, shares, new_balance, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn paused(env: &Env, caller: &Address, flags: u32) {
+    env.events().publish(
+        (symbol_short!("pause"), caller.clone()),
+        (EVENT_VERSION, flags, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn upgraded(env: &Env, new_wasm_hash: &BytesN<32>) {
+    env.events().publish(
+        (symbol_short!("upgrade"),),
+        (EVENT_VERSION, new_wasm_hash.clone(), env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn migrated(env: &Env, records: u32, schema_version: u32) {
+    env.events().publish(
+        (symbol_short!("migrate"),),
+        (EVENT_VERSION, records, schema_version, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn rewards_funded(
+    env: &Env,
+    from: &Address,
+    amount: i128,
+    rate: i128,
+    period_finish: u64,
+    token: &Address,
+    new_balance: i128,
+) {
+    env.events().publish(
+        (symbol_short!("fund"), from.clone()),
+        (EVENT_VERSION, amount, rate, period_finish, env.ledger().timestamp(), token.clone(), new_balance),
+    );
+}
+
+pub(crate) fn reward_token_added(env: &Env, token: &Address) {
+    env.events().publish(
+        (symbol_short!("rwd_add"), token.clone()),
+        (EVENT_VERSION, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn reward_token_removed(env: &Env, token: &Address, refunded: i128) {
+    env.events().publish(
+        (symbol_short!("rwd_rm"), token.clone()),
+        (EVENT_VERSION, refunded, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn reward_claimed(env: &Env, user: &Address, token: &Address, amount: i128) {
+    env.events().publish(
+        (symbol_short!("rwd_claim"), user.clone()),
+        (EVENT_VERSION, token.clone(), amount, env.ledger().timestamp()),
+    );
+}
+
+pub(crate) fn deficit(env: &Env, staked_deficit: i128, steth_deficit: i128) {
+    env.events().publish(
+        (symbol_short!("deficit"),),
+        (EVENT_VERSION, staked_deficit, steth_deficit, env.ledger().timestamp()),
+    );
+}
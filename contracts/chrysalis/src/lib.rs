@@ -1,25 +1,42 @@
 #![no_std]
 
+mod checkpoints;
 mod contract_interface;
+mod delegation;
+mod error;
+mod events;
+mod lock;
+mod migration;
+mod operator;
+mod pause;
+mod pool;
+mod reward_tokens;
+mod solvency;
+mod storage;
 mod test;
+mod ve;
+mod withdrawal;
 
-use soroban_sdk::{contract, contractimpl, contracttype, events, log, Address, Env, IntoVal, Symbol, Vec};
+use soroban_sdk::{contract, contractimpl, contracttype, log, token, Address, BytesN, Env, IntoVal, Symbol, Vec};
 use contract_interface::ChrysalisContractTrait;
+pub use error::ChrysalisError;
+pub use lock::{BoostCurve, Lock};
+pub use reward_tokens::RewardPool;
+pub use solvency::SolvencyReport;
+pub use withdrawal::WithdrawalRequest;
 
 #[contracttype]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct Stake {
     amount: i128,
     timestamp: u64,
-}
-
-impl Default for Stake {
-    fn default() -> Self {
-        Stake {
-            amount: 0,
-            timestamp: 0,
-        }
-    }
+    /// `amount * acc_reward_per_share` at the last settlement, i.e. the part of
+    /// the global index this stake has already been credited for.
+    reward_debt: i128,
+    /// Rewards settled into the stake but not yet claimed.
+    pending_rewards: i128,
+    /// Rewards paid out to the user over the lifetime of the stake.
+    claimed_total: i128,
 }
 
 
@@ -32,189 +49,1302 @@ enum DataKey {
     Stake(Address),
     StakedTokenAddress,
     StETHAddress,
+    Admin,
+    PendingAdmin,
+    RewardRate,
+    AccRewardPerShare,
+    LastUpdateTimestamp,
+    TotalStaked,
+    TotalPooled,
+    TotalShares,
+    UnbondingPeriod,
+    NextWithdrawalId,
+    Withdrawal(u64),
+    UserWithdrawals(Address),
+    Paused,
+    Guardian,
+    SchemaVersion,
+    LegacyRemaining,
+    EscrowedShares,
+    TotalRewardsPaid,
+    StakerCount,
+    TotalRewardsEmitted,
+    RewardBudget,
+    PeriodFinish,
+    RewardTokens,
+    RewardPool(Address),
+    UserReward(Address, Address),
+    AutoCompound(Address),
+    BoostCurve,
+    Locks(Address),
+    NextLockId,
+    TotalWeight,
+    Treasury,
+    VePoint(u32),
+    VePointCount,
+    UserVePoint(Address, u32),
+    UserVePointCount(Address),
+    SlopeChange(u64),
+    UserSlopeChange(Address, u64),
+    StakeCheckpoint(Address, u32),
+    StakeCheckpointCount(Address),
+    TotalStakedCheckpoint(u32),
+    TotalStakedCheckpointCount,
+    Delegate(Address),
+    DelegatedVePoint(Address, u32),
+    DelegatedVePointCount(Address),
+    DelegatedSlopeChange(Address, u64),
+    Operator(Address, Address),
+}
+
+/// Fixed-point scale applied to `acc_reward_per_share`.
+pub(crate) const REWARD_PRECISION: i128 = 1_000_000_000_000;
+
+/// Maximum number of users `compound_batch` processes in one call.
+pub(crate) const MAX_COMPOUND_BATCH: u32 = 25;
+
+fn read_admin(env: &Env) -> Result<Address, ChrysalisError> {
+    env.storage().instance().get(&DataKey::Admin).ok_or(ChrysalisError::NotInitialized)
+}
+
+pub(crate) fn read_address(env: &Env, key: &DataKey) -> Result<Address, ChrysalisError> {
+    env.storage().instance().get(key).ok_or(ChrysalisError::NotInitialized)
+}
+
+fn read_reward_rate(env: &Env) -> i128 {
+    env.storage().instance().get(&DataKey::RewardRate).unwrap_or(0)
+}
+
+pub(crate) fn read_total_staked(env: &Env) -> i128 {
+    env.storage().instance().get(&DataKey::TotalStaked).unwrap_or(0)
+}
+
+/// Sum of every stake's reward weight. Equals `TotalStaked` until the first lock.
+pub(crate) fn read_total_weight(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::TotalWeight)
+        .unwrap_or_else(|| read_total_staked(env))
+}
+
+pub(crate) fn read_total_rewards_paid(env: &Env) -> i128 {
+    env.storage().instance().get(&DataKey::TotalRewardsPaid).unwrap_or(0)
+}
+
+fn read_staker_count(env: &Env) -> u32 {
+    env.storage().instance().get(&DataKey::StakerCount).unwrap_or(0)
+}
+
+fn read_total_rewards_emitted(env: &Env) -> i128 {
+    env.storage().instance().get(&DataKey::TotalRewardsEmitted).unwrap_or(0)
+}
+
+/// Funded rewards not yet emitted, as of the last index update.
+fn read_reward_budget(env: &Env) -> i128 {
+    env.storage().instance().get(&DataKey::RewardBudget).unwrap_or(0)
+}
+
+fn read_period_finish(env: &Env) -> u64 {
+    env.storage().instance().get(&DataKey::PeriodFinish).unwrap_or(0)
+}
+
+/// Returns the rewards emitted since the index was last updated.
+///
+/// Emission stops at `PeriodFinish` and never exceeds the funded budget.
+fn unaccrued_emission(env: &Env) -> i128 {
+    let last_update: u64 = env.storage().instance().get(&DataKey::LastUpdateTimestamp).unwrap_or(0);
+    let end = env.ledger().timestamp().min(read_period_finish(env));
+
+    // Nothing is emitted while nobody is staked
+    if end <= last_update || read_total_weight(env) == 0 {
+        return 0;
+    }
+    (read_reward_rate(env) * (end - last_update) as i128).min(read_reward_budget(env))
+}
+
+/// Returns the rewards emitted over the lifetime of the contract, up to the
+/// current ledger timestamp.
+pub(crate) fn current_rewards_emitted(env: &Env) -> i128 {
+    read_total_rewards_emitted(env) + unaccrued_emission(env)
+}
+
+/// Spreads the remaining budget at the current rate. Must be called right
+/// after the reward index has been updated.
+fn reset_period_finish(env: &Env) {
+    let rate = read_reward_rate(env);
+    let mut period_finish = env.ledger().timestamp();
+    if rate > 0 {
+        period_finish += (read_reward_budget(env) / rate) as u64;
+    }
+    env.storage().instance().set(&DataKey::PeriodFinish, &period_finish);
+}
+
+/// Returns the funded rewards not yet emitted, up to the current ledger timestamp.
+pub(crate) fn reward_budget_remaining(env: &Env) -> i128 {
+    read_reward_budget(env) - unaccrued_emission(env)
+}
+
+/// Returns the reward index as it would be after accruing up to the current
+/// ledger timestamp, without writing it back.
+fn current_reward_index(env: &Env) -> i128 {
+    let acc_reward_per_share: i128 = env.storage().instance().get(&DataKey::AccRewardPerShare).unwrap_or(0);
+    let emission = unaccrued_emission(env);
+    if emission == 0 {
+        return acc_reward_per_share;
+    }
+    acc_reward_per_share + emission * REWARD_PRECISION / read_total_weight(env)
+}
+
+/// Accrues the reward index up to the current ledger timestamp and returns it.
+fn update_reward_index(env: &Env) -> i128 {
+    let acc_reward_per_share = current_reward_index(env);
+    let emission = unaccrued_emission(env);
+    env.storage().instance().set(&DataKey::RewardBudget, &(read_reward_budget(env) - emission));
+    env.storage()
+        .instance()
+        .set(&DataKey::TotalRewardsEmitted, &(read_total_rewards_emitted(env) + emission));
+    env.storage().instance().set(&DataKey::AccRewardPerShare, &acc_reward_per_share);
+    env.storage().instance().set(&DataKey::LastUpdateTimestamp, &env.ledger().timestamp());
+    acc_reward_per_share
+}
+
+/// Returns the weight the stake earns rewards with: its shares plus the boost
+/// of its locks.
+pub(crate) fn read_weight(env: &Env, user: &Address, stake: &Stake) -> i128 {
+    stake.amount + lock::boost_weight(&lock::read_locks(env, user))
+}
+
+/// Moves everything the stake earned since its last settlement into `pending_rewards`.
+fn settle_rewards(stake: &mut Stake, weight: i128, acc_reward_per_share: i128) {
+    stake.pending_rewards += weight * acc_reward_per_share / REWARD_PRECISION - stake.reward_debt;
+    stake.reward_debt = weight * acc_reward_per_share / REWARD_PRECISION;
+}
+
+/// Settles the user's rewards, applies `delta` to their stake and keeps
+/// `TotalStaked`, `TotalWeight`, `StakerCount`, the balance checkpoints and
+/// delegated power in sync. Returns the updated stake, already written back.
+pub(crate) fn update_stake(env: &Env, user: &Address, delta: i128) -> Stake {
+    update_position(env, user, delta, lock::read_locks(env, user))
+}
+
+/// Same as `update_stake`, but also replaces the user's locks with `locks`.
+/// Expired locks are dropped.
+pub(crate) fn update_position(env: &Env, user: &Address, delta: i128, locks: Vec<Lock>) -> Stake {
+    let mut stakes: Stake = storage::read_stake(env, user);
+    let old_locks = lock::read_locks(env, user);
+    let old_weight = stakes.amount + lock::boost_weight(&old_locks);
+    let acc_reward_per_share = update_reward_index(env);
+    settle_rewards(&mut stakes, old_weight, acc_reward_per_share);
+
+    let locks = lock::write_locks(env, user, &locks);
+    if locks != old_locks {
+        ve::checkpoint_total(env, &old_locks, &locks);
+        delegation::on_locks_changed(env, user, &old_locks, &locks);
+    }
+    let new_weight = stakes.amount + delta + lock::boost_weight(&locks);
+    reward_tokens::update_user(env, user, old_weight, new_weight);
+
+    let was_staker = stakes.amount > 0;
+    stakes.amount += delta;
+    if was_staker != (stakes.amount > 0) {
+        let staker_count = if was_staker {
+            read_staker_count(env) - 1
+        } else {
+            read_staker_count(env) + 1
+        };
+        env.storage().instance().set(&DataKey::StakerCount, &staker_count);
+    }
+    if delta > 0 {
+        stakes.timestamp = env.ledger().timestamp();
+    }
+    stakes.reward_debt = new_weight * acc_reward_per_share / REWARD_PRECISION;
+    storage::write_stake(env, user, &stakes);
+    env.storage().instance().set(&DataKey::TotalWeight, &(read_total_weight(env) + new_weight - old_weight));
+    let total_staked = read_total_staked(env) + delta;
+    env.storage().instance().set(&DataKey::TotalStaked, &total_staked);
+    if delta != 0 {
+        checkpoints::write(env, user, stakes.amount, total_staked);
+    }
+    stakes
+}
+
+/// Drops the user's expired locks, so that they stop boosting the position,
+/// and publishes an unlock event for each. Returns the number of locks dropped.
+fn drop_expired_locks(env: &Env, user: &Address) -> u32 {
+    let locks = lock::read_locks(env, user);
+    let now = env.ledger().timestamp();
+    let mut expired = Vec::new(env);
+    for lock in locks.iter() {
+        if lock.unlock_time <= now {
+            expired.push_back(lock);
+        }
+    }
+    if expired.is_empty() {
+        return 0;
+    }
+
+    let stakes = update_position(env, user, 0, locks);
+    for lock in expired.iter() {
+        events::unlocked(env, user, lock.id, lock.amount, 0, stakes.amount);
+    }
+    expired.len()
+}
+
+/// Pays out the user's stETH rewards and returns the amount paid.
+fn claim_steth(env: &Env, user: &Address) -> Result<i128, ChrysalisError> {
+    drop_expired_locks(env, user);
+
+    // Fetch the user's current stake, or initialize to default if it doesn't exist
+    let mut stakes: Stake = storage::read_stake(env, user);
+
+    // Calculate rewards accrued through the global reward index
+    let acc_reward_per_share = update_reward_index(env);
+    let weight = read_weight(env, user, &stakes);
+    settle_rewards(&mut stakes, weight, acc_reward_per_share);
+    let rewards: i128 = stakes.pending_rewards;
+    stakes.pending_rewards = 0;
+
+    // Ensure rewards are non-zero
+    if rewards > 0 {
+        // Transfer rewards (stETH) to the user
+        let steth_address: Address = read_address(env, &DataKey::StETHAddress)?;
+        env.invoke_contract::<()>(
+            &steth_address,
+            &Symbol::new(env, "transfer"),
+            Vec::from_array(
+                env,
+                [
+                    env.current_contract_address().into_val(env),  // From the contract
+                    user.into_val(env),                             // To the user
+                    rewards.into_val(env),
+                ]
+            ),
+        );
+
+        // Rewards are tracked separately; the principal is left untouched
+        stakes.claimed_total += rewards;
+        env.storage().instance().set(&DataKey::TotalRewardsPaid, &(read_total_rewards_paid(env) + rewards));
+        log!(env, "Claimed Total: {}", stakes.claimed_total);
+
+        // Update storage with the settled rewards
+        storage::write_stake(env, user, &stakes);
+        events::claimed(env, user, rewards, stakes.amount);
+    } else if storage::has_stake(env, user) {
+        storage::bump_stake(env, user);
+    }
+    Ok(rewards)
+}
+
+/// Takes `amount` of the staked token from `payer` and mints stETH shares at the
+/// current exchange rate. Returns the shares minted; the caller records them in
+/// a stake.
+///
+/// Staked shares are held by the contract, not by the staker, so that a stake
+/// can only leave through the contract and is always backed by the shares it
+/// records.
+fn deposit(env: &Env, payer: &Address, amount: i128) -> Result<i128, ChrysalisError> {
+    pause::require_not_paused(env, pause::PAUSE_DEPOSITS)?;
+    if amount <= 0 {
+        return Err(ChrysalisError::InvalidAmount);
+    }
+    let staked_token_address: Address = read_address(env, &DataKey::StakedTokenAddress)?;
+    let steth_address: Address = read_address(env, &DataKey::StETHAddress)?;
+
+    let balance: i128 = env.invoke_contract(
+        &staked_token_address,
+        &Symbol::new(env, "balance"),
+        (payer,).into_val(env),
+    );
+
+    log!(env, "Balance: {}", balance);
+
+    if balance < amount {
+        return Err(ChrysalisError::InsufficientBalance);
+    }
+
+    // Transfer staked ETH from the payer to the contract
+    env.invoke_contract::<()>(
+        &staked_token_address,
+        &Symbol::new(env, "transfer"),
+        Vec::from_array(
+            env,
+            [
+                payer.into_val(env),
+                env.current_contract_address().into_val(env),
+                amount.into_val(env),
+            ]
+        ),
+    );
+
+    // Mint stETH shares at the current exchange rate; Chrysalis is the issuing
+    // admin of the stETH asset
+    let shares = pool::shares_for_amount(env, amount);
+    if shares <= 0 {
+        return Err(ChrysalisError::InvalidAmount);
+    }
+    token::StellarAssetClient::new(env, &steth_address).mint(&env.current_contract_address(), &shares);
+    pool::write_totals(
+        env,
+        pool::read_total_pooled(env) + amount,
+        pool::read_total_shares(env) + shares,
+    );
+    Ok(shares)
+}
+
+/// Restakes the user's stETH rewards and returns the amount compounded.
+///
+/// Rewards are paid out of the reward budget, whose stETH is already part of the
+/// share supply, so compounding moves those shares into the user's position
+/// without touching the pool totals.
+fn compound_rewards(env: &Env, user: &Address) -> Result<i128, ChrysalisError> {
+    drop_expired_locks(env, user);
+    let mut stakes: Stake = storage::read_stake(env, user);
+    let weight = read_weight(env, user, &stakes);
+    settle_rewards(&mut stakes, weight, update_reward_index(env));
+    let rewards = stakes.pending_rewards;
+    if rewards <= 0 {
+        return Ok(0);
+    }
+    stakes.pending_rewards = 0;
+    stakes.claimed_total += rewards;
+    storage::write_stake(env, user, &stakes);
+    env.storage().instance().set(&DataKey::TotalRewardsPaid, &(read_total_rewards_paid(env) + rewards));
+
+    // Restake as if the rewards were claimed and deposited again: the reward
+    // shares are burned for their underlying, which mints new shares into the
+    // user's stake, so every staked share stays backed by an outstanding one
+    let total_pooled = pool::read_total_pooled(env);
+    let underlying = pool::amount_for_shares(env, rewards);
+    pool::write_totals(env, total_pooled - underlying, pool::read_total_shares(env) - rewards);
+    let restaked = pool::shares_for_amount(env, underlying);
+    pool::write_totals(env, total_pooled, pool::read_total_shares(env) + restaked);
+
+    let steth_address: Address = read_address(env, &DataKey::StETHAddress)?;
+    token::Client::new(env, &steth_address).burn(&env.current_contract_address(), &rewards);
+    token::StellarAssetClient::new(env, &steth_address).mint(&env.current_contract_address(), &restaked);
+    let stakes = update_stake(env, user, restaked);
+    events::compounded(env, user, restaked, stakes.amount);
+    Ok(restaked)
+}
+
+/// Splits `amount` stETH taken from the user into the part paid from the stETH
+/// they hold, such as claimed rewards, and the part taken out of their stake.
+/// Held stETH is used first unless `stake_only` is set, and locked shares are
+/// never used.
+fn split_debit(
+    env: &Env,
+    user: &Address,
+    amount: i128,
+    stake_only: bool,
+) -> Result<(i128, i128), ChrysalisError> {
+    let held = if stake_only {
+        0
+    } else {
+        let steth_address: Address = read_address(env, &DataKey::StETHAddress)?;
+        token::Client::new(env, &steth_address).balance(user).min(amount)
+    };
+    let debit = amount - held;
+    let staked = storage::read_stake(env, user).amount;
+    if staked < debit {
+        return Err(ChrysalisError::InsufficientStake);
+    }
+    if staked - lock::locked_amount(env, user) < debit {
+        return Err(ChrysalisError::StakeLocked);
+    }
+    Ok((held, debit))
+}
+
+/// Redeems `amount` of the user's shares and pays the staked token to the user.
+///
+/// An operator only manages the user's position, so when `caller` is not the
+/// user the shares come out of the stake alone, within the operator's unstake
+/// allowance.
+fn redeem(env: &Env, caller: &Address, user: &Address, amount: i128) -> Result<(), ChrysalisError> {
+    if amount <= 0 {
+        return Err(ChrysalisError::InvalidAmount);
+    }
+
+    // Retrieve staked token addresses
+    let staked_token_address: Address = read_address(env, &DataKey::StakedTokenAddress)?;
+    let steth_address: Address = read_address(env, &DataKey::StETHAddress)?;
+
+    // Withdrawals go through the queue while an unbonding period is configured
+    if withdrawal::read_unbonding_period(env) > 0 {
+        return Err(ChrysalisError::UnbondingRequired);
+    }
+
+    // Ensure sufficient stETH to unstake; held stETH is redeemed before staked shares
+    let (held, stake_debit) = split_debit(env, user, amount, caller != user)?;
+    if caller != user {
+        operator::spend_unstake_allowance(env, user, caller, amount)?;
+    }
+
+    // Redeem the shares for their proportional part of the pool
+    let underlying = pool::amount_for_shares(env, amount);
+    pool::write_totals(
+        env,
+        pool::read_total_pooled(env) - underlying,
+        pool::read_total_shares(env) - amount,
+    );
+
+    // Burn the redeemed stETH, from the user's hands and from custody, so supply
+    // always equals outstanding shares
+    let steth = token::Client::new(env, &steth_address);
+    if held > 0 {
+        steth.burn(user, &held);
+    }
+    if stake_debit > 0 {
+        steth.burn(&env.current_contract_address(), &stake_debit);
+    }
+
+    // Transfer staked ETH to user
+    env.invoke_contract::<()>(
+        &staked_token_address,
+        &Symbol::new(env, "transfer"),
+        Vec::from_array(
+            env,
+            [
+                env.current_contract_address().into_val(env),  // From the contract
+                user.into_val(env),                             // To the user
+                underlying.into_val(env),
+            ]
+        ),
+    );
+
+    // Subtract the staked part of the redeemed shares from the user's stake
+    let stakes: Stake = if stake_debit > 0 {
+        update_stake(env, user, -stake_debit)
+    } else {
+        storage::read_stake(env, user)
+    };
+    log!(env, "Updated Balance: {}", stakes.amount);
+    events::unstaked(env, user, underlying, stakes.amount, amount);
+    Ok(())
+}
+
+fn read_treasury(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKey::Treasury)
+}
+
+fn read_auto_compound(env: &Env, user: &Address) -> bool {
+    env.storage().persistent().get(&DataKey::AutoCompound(user.clone())).unwrap_or(false)
 }
 
 #[contractimpl]
 impl ChrysalisContractTrait for ChrysalisContract {
     
-    fn initialize_contract(env: Env, staked_token_address: Address, steth_address: Address) {
+    fn initialize_contract(env: Env, admin: Address, staked_token_address: Address, steth_address: Address) -> Result<(), ChrysalisError> {
+        // The deployer must prove control of the admin account
+        admin.require_auth();
+        storage::extend_instance(&env);
+
         // Check if the contract is already initialized
         if env.storage().instance().has(&DataKey::StakedTokenAddress) {
-            panic!("Contract is already initialized");
+            return Err(ChrysalisError::AlreadyInitialized);
         }
     
         // Set the addresses if not initialized
+        env.storage().instance().set(&DataKey::Admin, &admin);
         env.storage().instance().set(&DataKey::StakedTokenAddress, &staked_token_address);
         env.storage().instance().set(&DataKey::StETHAddress, &steth_address);
+        env.storage().instance().set(&DataKey::LastUpdateTimestamp, &env.ledger().timestamp());
+        migration::write_schema_version(&env, migration::CURRENT_SCHEMA_VERSION);
+        events::initialized(&env, &admin, &staked_token_address, &steth_address);
+        Ok(())
     }
-    
 
-    fn stake_eth(env: Env, user: Address, amount: i128) {
-        user.require_auth();
-        let staked_token_address: Address = env.storage().instance().get(&DataKey::StakedTokenAddress).unwrap();
-        let steth_address: Address = env.storage().instance().get(&DataKey::StETHAddress).unwrap();
+    fn propose_admin(env: Env, new_admin: Address) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
 
-        let balance: i128 = env.invoke_contract(
-            &staked_token_address,
-            &Symbol::new(&env.clone(), "balance"),
-            (&user,).into_val(&env),
-        );
+        // The proposal only takes effect once `new_admin` accepts it
+        env.storage().instance().set(&DataKey::PendingAdmin, &new_admin);
+        events::admin_proposed(&env, &admin, &new_admin);
+        Ok(())
+    }
 
-        log!(&env, "Balance: {}", balance);
+    fn accept_admin(env: Env) -> Result<(), ChrysalisError> {
+        let pending_admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::PendingAdmin)
+            .ok_or(ChrysalisError::NoPendingAdmin)?;
+        pending_admin.require_auth();
+        storage::extend_instance(&env);
+        let previous_admin = read_admin(&env)?;
 
-        assert!(balance >= amount, "Insufficient balance");
+        env.storage().instance().set(&DataKey::Admin, &pending_admin);
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+        events::admin_accepted(&env, &previous_admin, &pending_admin);
+        Ok(())
+    }
 
-        // Transfer staked ETH from user to contract
-        env.invoke_contract::<()>(
-            &staked_token_address,
-            &Symbol::new(&env.clone(), "transfer"),
-            Vec::from_array(
-                &env,
-                [
-                    user.into_val(&env.clone()),
-                    env.current_contract_address().into_val(&env.clone()),
-                    amount.into_val(&env.clone()),
-                ]
-            ),
-        );
+    fn get_admin(env: Env) -> Result<Address, ChrysalisError> {
+        read_admin(&env)
+    }
 
-        // Transfer stETH from the contract's address to the user (instead of minting)
-        env.invoke_contract::<()>(
-            &steth_address,
-            &Symbol::new(&env.clone(), "transfer"),
-            Vec::from_array(
+    fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        env.deployer().update_current_contract_wasm(new_wasm_hash.clone());
+        events::upgraded(&env, &new_wasm_hash);
+        Ok(())
+    }
+
+    fn begin_migration(env: Env, legacy_count: u32) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+        migration::begin(&env, legacy_count)
+    }
+
+    fn migrate(env: Env, users: Vec<Address>) -> Result<u32, ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        let migrated = migration::migrate_stakes(&env, &users)?;
+        events::migrated(&env, migrated, migration::read_schema_version(&env));
+        Ok(migrated)
+    }
+
+    fn get_legacy_remaining(env: Env) -> u32 {
+        migration::read_legacy_remaining(&env).unwrap_or(0)
+    }
+
+    fn version(env: Env) -> u32 {
+        migration::read_schema_version(&env)
+    }
+
+    fn set_reward_rate(env: Env, rate: i128) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        if rate < 0 {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+
+        // Accrue at the old rate first so the change only applies going forward
+        update_reward_index(&env);
+        let old_rate = read_reward_rate(&env);
+        env.storage().instance().set(&DataKey::RewardRate, &rate);
+
+        reset_period_finish(&env);
+        events::reward_rate_set(&env, old_rate, rate);
+        Ok(())
+    }
+
+    fn fund_rewards(env: Env, from: Address, amount: i128, duration: u64) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        from.require_auth();
+        storage::extend_instance(&env);
+        if amount <= 0 || duration == 0 {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+
+        // Settle the current period, then roll whatever is left of its budget
+        // into the new one
+        update_reward_index(&env);
+        let budget = read_reward_budget(&env) + amount;
+        let rate = budget / duration as i128;
+        if rate == 0 {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+        // The funded stETH leaves the funder's stake, so it stops earning for them;
+        // staked shares are already in the contract's custody
+        let (held, stake_debit) = split_debit(&env, &from, amount, false)?;
+        let steth_address: Address = read_address(&env, &DataKey::StETHAddress)?;
+        if held > 0 {
+            token::Client::new(&env, &steth_address).transfer(&from, &env.current_contract_address(), &held);
+        }
+        let stakes: Stake = if stake_debit > 0 {
+            update_stake(&env, &from, -stake_debit)
+        } else {
+            storage::read_stake(&env, &from)
+        };
+
+        let period_finish = env.ledger().timestamp() + duration;
+        env.storage().instance().set(&DataKey::RewardBudget, &budget);
+        env.storage().instance().set(&DataKey::RewardRate, &rate);
+        env.storage().instance().set(&DataKey::PeriodFinish, &period_finish);
+        events::rewards_funded(&env, &from, amount, rate, period_finish, &steth_address, stakes.amount);
+        Ok(())
+    }
+
+    fn get_reward_budget_remaining(env: Env) -> i128 {
+        reward_budget_remaining(&env)
+    }
+
+    fn get_period_finish(env: Env) -> u64 {
+        read_period_finish(&env)
+    }
+
+    fn get_reward_rate(env: Env) -> i128 {
+        read_reward_rate(&env)
+    }
+
+    fn set_guardian(env: Env, guardian: Address) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        env.storage().instance().set(&DataKey::Guardian, &guardian);
+        Ok(())
+    }
+
+    fn get_guardian(env: Env) -> Option<Address> {
+        pause::read_guardian(&env)
+    }
+
+    fn pause(env: Env, caller: Address, flags: u32) -> Result<(), ChrysalisError> {
+        pause::require_pauser(&env, &caller)?;
+        storage::extend_instance(&env);
+
+        let paused = pause::read_paused(&env) | (flags & pause::PAUSE_ALL);
+        pause::write_paused(&env, paused);
+        events::paused(&env, &caller, paused);
+        Ok(())
+    }
+
+    fn unpause(env: Env, caller: Address, flags: u32) -> Result<(), ChrysalisError> {
+        pause::require_pauser(&env, &caller)?;
+        storage::extend_instance(&env);
+
+        let paused = pause::read_paused(&env) & !flags;
+        pause::write_paused(&env, paused);
+        events::paused(&env, &caller, paused);
+        Ok(())
+    }
+
+    fn get_paused(env: Env) -> u32 {
+        pause::read_paused(&env)
+    }
+
+    fn check_solvency(env: Env) -> Result<SolvencyReport, ChrysalisError> {
+        solvency::report(&env)
+    }
+
+    fn reconcile(env: Env) -> Result<SolvencyReport, ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        let report = solvency::report(&env)?;
+        if !report.solvent {
+            events::deficit(
                 &env,
-                [
-                    env.current_contract_address().into_val(&env.clone()),  // From the contract's balance
-                    user.into_val(&env.clone()),                             // To the user
-                    amount.into_val(&env.clone()),
-                ]
-            ),
-        );
+                (report.staked_liability - report.staked_balance).max(0),
+                (report.steth_liability - report.steth_balance).max(0),
+            );
+            // Stop all flows until the admin has restored the backing
+            let paused = pause::read_paused(&env) | pause::PAUSE_ALL;
+            pause::write_paused(&env, paused);
+            events::paused(&env, &admin, paused);
+        }
+        Ok(report)
+    }
+    
+
+    fn stake_eth(env: Env, user: Address, amount: i128) -> Result<(), ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+        let shares = deposit(&env, &user, amount)?;
 
         // Update user's stake information
-        let mut stakes: Stake = env.storage().instance().get(&DataKey::Stake(user.clone())).unwrap_or_default();
-        
-        stakes.amount += amount;
-        stakes.timestamp = env.ledger().timestamp();
-        env.storage().instance().set(&DataKey::Stake(user), &stakes);
+        let stakes: Stake = update_stake(&env, &user, shares);
+        events::staked(&env, &user, amount, stakes.amount, shares, &user);
+        Ok(())
     }
 
-    
-    fn unstake_eth(env: Env, user: Address, amount: i128) -> () {
+    fn stake_for(env: Env, payer: Address, beneficiary: Address, amount: i128) -> Result<(), ChrysalisError> {
+        payer.require_auth();
+        storage::extend_instance(&env);
+        let shares = deposit(&env, &payer, amount)?;
+
+        let stakes: Stake = update_stake(&env, &beneficiary, shares);
+        events::staked(&env, &beneficiary, amount, stakes.amount, shares, &payer);
+        Ok(())
+    }
+
+    fn stake_steth(env: Env, user: Address, shares: i128) -> Result<(), ChrysalisError> {
         user.require_auth();
-    
-        // Retrieve staked token addresses
-        let staked_token_address: Address = env.storage().instance().get(&DataKey::StakedTokenAddress).unwrap();
-        let steth_address: Address = env.storage().instance().get(&DataKey::StETHAddress).unwrap();
-    
-        // Get the user's current stake
-        let mut stakes: Stake = env.storage().instance().get(&DataKey::Stake(user.clone())).unwrap_or_default();
-    
-        // Ensure sufficient stake balance to unstake
-        if stakes.amount < amount {
-            panic!("Insufficient balance to unstake!");
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_DEPOSITS)?;
+        if shares <= 0 {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+        let steth_address: Address = read_address(&env, &DataKey::StETHAddress)?;
+        let steth = token::Client::new(&env, &steth_address);
+        if steth.balance(&user) < shares {
+            return Err(ChrysalisError::InsufficientBalance);
         }
+
+        // The shares go into the contract's custody, like freshly minted ones
+        steth.transfer(&user, &env.current_contract_address(), &shares);
+        let stakes: Stake = update_stake(&env, &user, shares);
+        events::staked(&env, &user, pool::amount_for_shares(&env, shares), stakes.amount, shares, &user);
+        Ok(())
+    }
+
     
-        // Transfer stETH back from user to contract (instead of burning)
-        env.invoke_contract::<()>(
-            &steth_address,
-            &Symbol::new(&env.clone(), "transfer"),
-            Vec::from_array(
-                &env,
-                [
-                    user.into_val(&env.clone()),                             // From the user
-                    env.current_contract_address().into_val(&env.clone()),  // Back to the contract
-                    amount.into_val(&env.clone()),
-                ]
-            ),
-        );
+    fn stake_locked(env: Env, user: Address, amount: i128, lock_duration: u64) -> Result<u64, ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+        let shares = deposit(&env, &user, amount)?;
+
+        let mut locks = lock::read_locks(&env, &user);
+        let new_lock = lock::create_lock(&env, &mut locks, shares, lock_duration)?;
+        let stakes: Stake = update_position(&env, &user, shares, locks);
+        events::staked(&env, &user, amount, stakes.amount, shares, &user);
+        events::locked(&env, &user, &new_lock);
+        Ok(new_lock.id)
+    }
+
+    fn extend_lock(env: Env, user: Address, lock_id: u64, lock_duration: u64) -> Result<(), ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+
+        let mut locks = lock::read_locks(&env, &user);
+        let index = lock::find_lock(&locks, lock_id)?;
+        let mut extended = locks.get_unchecked(index);
+        let unlock_time = env.ledger().timestamp() + lock_duration;
+        if unlock_time <= extended.unlock_time {
+            return Err(ChrysalisError::InvalidLockDuration);
+        }
+        // The boost follows the new commitment, measured from now
+        extended.boost = lock::boost_for(&env, lock_duration)?;
+        extended.unlock_time = unlock_time;
+        locks.set(index, extended.clone());
+        update_position(&env, &user, 0, locks);
+        events::locked(&env, &user, &extended);
+        Ok(())
+    }
+
+    fn early_unlock(env: Env, user: Address, lock_id: u64) -> Result<i128, ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_WITHDRAWALS)?;
+
+        let mut locks = lock::read_locks(&env, &user);
+        let index = lock::find_lock(&locks, lock_id)?;
+        let released = locks.get_unchecked(index);
+        let penalty = lock::early_unlock_penalty(&env, &released);
+        locks.remove(index);
+        let stakes: Stake = update_position(&env, &user, -penalty, locks);
+
+        if penalty > 0 {
+            match read_treasury(&env) {
+                Some(treasury) => {
+                    // The treasury takes over the forfeited shares, which stay in
+                    // custody, as a stake of its own
+                    let treasury_stake = update_stake(&env, &treasury, penalty);
+                    let amount = pool::amount_for_shares(&env, penalty);
+                    events::staked(&env, &treasury, amount, treasury_stake.amount, penalty, &user);
+                }
+                None => {
+                    // The forfeited shares stay in custody and join the reward
+                    // budget, which `update_position` has just settled. A running
+                    // period emits them by its end; otherwise they are rolled
+                    // into the next `fund_rewards`
+                    let budget = read_reward_budget(&env) + penalty;
+                    env.storage().instance().set(&DataKey::RewardBudget, &budget);
+                    let now = env.ledger().timestamp();
+                    let period_finish = read_period_finish(&env);
+                    if period_finish > now {
+                        let rate = budget / (period_finish - now) as i128;
+                        env.storage().instance().set(&DataKey::RewardRate, &rate);
+                    }
+                    let steth_address: Address = read_address(&env, &DataKey::StETHAddress)?;
+                    let rate = read_reward_rate(&env);
+                    events::rewards_funded(&env, &user, penalty, rate, period_finish, &steth_address, stakes.amount);
+                }
+            }
+        }
+        events::unlocked(&env, &user, lock_id, released.amount - penalty, penalty, stakes.amount);
+        Ok(penalty)
+    }
+
+    fn set_treasury(env: Env, treasury: Option<Address>) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        match treasury {
+            Some(treasury) => env.storage().instance().set(&DataKey::Treasury, &treasury),
+            None => env.storage().instance().remove(&DataKey::Treasury),
+        }
+        Ok(())
+    }
+
+    fn get_treasury(env: Env) -> Option<Address> {
+        read_treasury(&env)
+    }
+
+    fn delegate(env: Env, user: Address, delegatee: Address) -> Result<(), ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+
+        delegation::set_delegate(&env, &user, &delegatee);
+        Ok(())
+    }
+
+    fn get_delegate(env: Env, user: Address) -> Option<Address> {
+        delegation::read_delegate(&env, &user)
+    }
+
+    fn get_delegated_power(env: Env, delegatee: Address) -> i128 {
+        delegation::read_power(&env, &delegatee)
+    }
+
+    fn get_delegated_power_at(env: Env, delegatee: Address, timestamp: u64) -> i128 {
+        ve::delegated_power_at(&env, &delegatee, timestamp)
+    }
+
+    fn get_voting_power(env: Env, user: Address) -> i128 {
+        ve::voting_power_at(&env, &user, env.ledger().timestamp())
+    }
+
+    fn get_voting_power_at(env: Env, user: Address, timestamp: u64) -> i128 {
+        ve::voting_power_at(&env, &user, timestamp)
+    }
+
+    fn get_total_voting_power(env: Env) -> i128 {
+        ve::total_voting_power_at(&env, env.ledger().timestamp())
+    }
+
+    fn get_locks(env: Env, user: Address) -> Vec<Lock> {
+        lock::read_locks(&env, &user)
+    }
+
+    fn set_boost_curve(env: Env, max_boost: u32, max_lock_duration: u64) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        lock::write_curve(&env, &BoostCurve { max_boost, max_lock_duration })
+    }
+
+    fn get_boost_curve(env: Env) -> BoostCurve {
+        lock::read_curve(&env)
+    }
+
+    fn unstake_eth(env: Env, caller: Address, user: Address, amount: i128) -> Result<(), ChrysalisError> {
+        operator::require_caller(&env, &caller, &user, operator::OPERATOR_UNSTAKE)?;
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_WITHDRAWALS)?;
+        redeem(&env, &caller, &user, amount)
+    }
     
-        // Transfer staked ETH to user
-        env.invoke_contract::<()>(
-            &staked_token_address,
-            &Symbol::new(&env.clone(), "transfer"),
-            Vec::from_array(
-                &env,
-                [
-                    env.current_contract_address().into_val(&env.clone()),  // From the contract
-                    user.into_val(&env.clone()),                             // To the user
-                    amount.into_val(&env.clone()),
-                ]
-            ),
+
+    fn distribute_yield(env: Env, from: Address, amount: i128) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        from.require_auth();
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_DEPOSITS)?;
+        if amount <= 0 {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+        if pool::read_total_shares(&env) == 0 {
+            // Yield without shareholders would be captured by the next depositor
+            return Err(ChrysalisError::NoStake);
+        }
+        let staked_token_address: Address = read_address(&env, &DataKey::StakedTokenAddress)?;
+
+        token::Client::new(&env, &staked_token_address).transfer(&from, &env.current_contract_address(), &amount);
+        let total_pooled = pool::read_total_pooled(&env) + amount;
+        pool::write_totals(&env, total_pooled, pool::read_total_shares(&env));
+        events::yield_distributed(&env, &from, amount, total_pooled);
+        Ok(())
+    }
+
+    fn restore_principal(env: Env, user: Address, principal: i128) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        if !storage::has_stake(&env, &user) {
+            return Err(ChrysalisError::NoStake);
+        }
+        let current = storage::read_stake(&env, &user).amount;
+        if principal < current {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+
+        // Restored stake must be backed by shares the contract holds but owes to nobody
+        let restored = principal - current;
+        let report = solvency::report(&env)?;
+        if report.steth_balance - report.steth_liability < restored {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+
+        let stakes: Stake = update_stake(&env, &user, restored);
+        events::principal_restored(&env, &user, restored, stakes.amount);
+        Ok(())
+    }
+
+    fn get_total_staked(env: Env) -> i128 {
+        read_total_staked(&env)
+    }
+
+    fn get_total_pooled(env: Env) -> i128 {
+        pool::read_total_pooled(&env)
+    }
+
+    fn get_total_rewards_paid(env: Env) -> i128 {
+        read_total_rewards_paid(&env)
+    }
+
+    fn get_stake_at(env: Env, user: Address, ledger: u32) -> i128 {
+        checkpoints::stake_at(&env, &user, ledger)
+    }
+
+    fn get_total_staked_at(env: Env, ledger: u32) -> i128 {
+        checkpoints::total_staked_at(&env, ledger)
+    }
+
+    fn get_staker_count(env: Env) -> u32 {
+        read_staker_count(&env)
+    }
+
+    fn get_exchange_rate(env: Env) -> i128 {
+        pool::exchange_rate(&env)
+    }
+
+    fn preview_stake(env: Env, amount: i128) -> i128 {
+        pool::shares_for_amount(&env, amount)
+    }
+
+    fn preview_unstake(env: Env, shares: i128) -> i128 {
+        pool::amount_for_shares(&env, shares)
+    }
+
+    fn request_unstake(env: Env, user: Address, amount: i128) -> Result<u64, ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_WITHDRAWALS)?;
+        if amount <= 0 {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+        let steth_address: Address = read_address(&env, &DataKey::StETHAddress)?;
+        let (held, stake_debit) = split_debit(&env, &user, amount, false)?;
+
+        // The shares stop earning rewards and are escrowed until the request is
+        // claimed; staked shares are already in the contract's custody
+        let request = withdrawal::create_request(&env, &user, amount, stake_debit)?;
+        if held > 0 {
+            token::Client::new(&env, &steth_address).transfer(&user, &env.current_contract_address(), &held);
+        }
+        let stakes: Stake = update_stake(&env, &user, -stake_debit);
+        events::withdrawal_requested(&env, &user, request.id, amount, request.unlock_time, stakes.amount);
+        Ok(request.id)
+    }
+
+    fn claim_withdrawal(env: Env, user: Address, request_id: u64) -> Result<i128, ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_WITHDRAWALS)?;
+        let request = withdrawal::read_request(&env, request_id)?;
+        if request.owner != user {
+            return Err(ChrysalisError::Unauthorized);
+        }
+        if env.ledger().timestamp() < request.unlock_time {
+            return Err(ChrysalisError::WithdrawalNotReady);
+        }
+        let staked_token_address: Address = read_address(&env, &DataKey::StakedTokenAddress)?;
+        let steth_address: Address = read_address(&env, &DataKey::StETHAddress)?;
+
+        // Redeem the escrowed shares at the current exchange rate
+        let underlying = pool::amount_for_shares(&env, request.shares);
+        pool::write_totals(
+            &env,
+            pool::read_total_pooled(&env) - underlying,
+            pool::read_total_shares(&env) - request.shares,
         );
-    
-        // Subtract the unstaked amount from the user's stake
-        stakes.amount -= amount;
-        log!(&env, "Updated Balance: {}", stakes.amount);
-    
-        // Update storage with the new stake amount
-        env.storage().instance().set(&DataKey::Stake(user.clone()), &stakes);
+        withdrawal::remove_request(&env, &request);
+
+        token::Client::new(&env, &steth_address).burn(&env.current_contract_address(), &request.shares);
+        token::Client::new(&env, &staked_token_address).transfer(&env.current_contract_address(), &user, &underlying);
+        events::withdrawal_claimed(&env, &user, request_id, request.shares, underlying);
+        Ok(underlying)
+    }
+
+    fn cancel_withdrawal(env: Env, user: Address, request_id: u64) -> Result<(), ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_WITHDRAWALS)?;
+        let request = withdrawal::read_request(&env, request_id)?;
+        if request.owner != user {
+            return Err(ChrysalisError::Unauthorized);
+        }
+        let steth_address: Address = read_address(&env, &DataKey::StETHAddress)?;
+
+        // Shares that came out of the stake return to it, in custody, and earn
+        // rewards again; the rest is handed back to the user
+        withdrawal::remove_request(&env, &request);
+        let held = request.shares - request.stake_shares;
+        if held > 0 {
+            token::Client::new(&env, &steth_address).transfer(&env.current_contract_address(), &user, &held);
+        }
+        let stakes: Stake = update_stake(&env, &user, request.stake_shares);
+        events::withdrawal_cancelled(&env, &user, request_id, request.shares, stakes.amount);
+        Ok(())
+    }
+
+    fn get_withdrawal_requests(env: Env, user: Address) -> Vec<WithdrawalRequest> {
+        let mut requests = Vec::new(&env);
+        for id in withdrawal::read_user_requests(&env, &user).iter() {
+            if let Ok(request) = withdrawal::read_request(&env, id) {
+                requests.push_back(request);
+            }
+        }
+        requests
+    }
+
+    fn set_unbonding_period(env: Env, period: u64) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        withdrawal::write_unbonding_period(&env, period);
+        Ok(())
+    }
+
+    fn get_unbonding_period(env: Env) -> u64 {
+        withdrawal::read_unbonding_period(&env)
+    }
+
+    fn bump_stake(env: Env, user: Address) -> Result<(), ChrysalisError> {
+        if !storage::has_stake(&env, &user) {
+            return Err(ChrysalisError::NoStake);
+        }
+        storage::extend_instance(&env);
+        drop_expired_locks(&env, &user);
+        storage::bump_stake(&env, &user);
+        lock::bump_locks(&env, &user);
+        Ok(())
+    }
+
+    fn kick(env: Env, user: Address) -> u32 {
+        storage::extend_instance(&env);
+        drop_expired_locks(&env, &user)
     }
-    
 
     fn get_stake_amount(env: Env, user: Address) -> i128 {
-        let stakes: Stake = env.storage().instance().get(&DataKey::Stake(user.clone())).unwrap_or_default();
+        let stakes: Stake = storage::read_stake(&env, &user);
         stakes.amount
     }
 
+    fn compound(env: Env, caller: Address, user: Address) -> Result<i128, ChrysalisError> {
+        operator::require_caller(&env, &caller, &user, operator::OPERATOR_COMPOUND)?;
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_CLAIMS | pause::PAUSE_DEPOSITS)?;
+        compound_rewards(&env, &user)
+    }
+
+    fn set_auto_compound(env: Env, user: Address, enabled: bool) -> Result<(), ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+
+        let key = DataKey::AutoCompound(user.clone());
+        if enabled {
+            env.storage().persistent().set(&key, &true);
+            storage::extend_persistent(&env, &key);
+        } else {
+            env.storage().persistent().remove(&key);
+        }
+        Ok(())
+    }
+
+    fn get_auto_compound(env: Env, user: Address) -> bool {
+        read_auto_compound(&env, &user)
+    }
+
+    fn compound_batch(env: Env, users: Vec<Address>) -> Result<u32, ChrysalisError> {
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_CLAIMS | pause::PAUSE_DEPOSITS)?;
+        if users.len() > MAX_COMPOUND_BATCH {
+            return Err(ChrysalisError::BatchTooLarge);
+        }
+
+        // Anyone may trigger this, so it only acts for users who opted in
+        let mut compounded = 0;
+        for user in users.iter() {
+            if read_auto_compound(&env, &user) && compound_rewards(&env, &user)? > 0 {
+                compounded += 1;
+            }
+        }
+        Ok(compounded)
+    }
+
    
-    fn claim(env: Env, user: Address , apy: i128) -> i128 {
-        // Ensure the user is authenticated
+    fn approve_operator(
+        env: Env,
+        user: Address,
+        operator: Address,
+        permissions: u32,
+        expiration_ledger: u32,
+        unstake_allowance: i128,
+    ) -> Result<(), ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+        operator::approve(&env, &user, &operator, permissions, expiration_ledger, unstake_allowance)?;
+        events::operator_changed(&env, &user, &operator, permissions, expiration_ledger);
+        Ok(())
+    }
+
+    fn revoke_operator(env: Env, user: Address, operator: Address) -> Result<(), ChrysalisError> {
         user.require_auth();
-        
-        // Fetch the user's current stake, or initialize to default if it doesn't exist
-        let mut stakes: Stake = env.storage().instance().get(&DataKey::Stake(user.clone())).unwrap_or_default();
-            
-        // Simple interest for rewards calculation: 5% reward rate
-        let reward_rate: i128 = apy; // 5% as an integer
-        let current_timestamp: u64 = env.ledger().timestamp();
-        let staking_duration: u64 = current_timestamp - stakes.timestamp;
-        
-        // Calculate rewards
-        let rewards: i128 = stakes.amount * reward_rate * staking_duration as i128 / 100 / 1_000_000;
-            
-        // Update the stake's timestamp to the current ledger timestamp
-        stakes.timestamp = current_timestamp;
-        
-        // Ensure rewards are non-zero
+        storage::extend_instance(&env);
+        operator::revoke(&env, &user, &operator);
+        events::operator_changed(&env, &user, &operator, 0, 0);
+        Ok(())
+    }
+
+    fn is_operator(env: Env, user: Address, operator: Address, permissions: u32) -> bool {
+        operator::is_approved(&env, &user, &operator, permissions)
+    }
+
+    fn transfer_stake(env: Env, from: Address, to: Address, amount: i128) -> Result<(), ChrysalisError> {
+        from.require_auth();
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_WITHDRAWALS)?;
+        if amount <= 0 || from == to {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+
+        // Locks stay with the sender, so only unlocked shares can move
+        let from_before: Stake = storage::read_stake(&env, &from);
+        if from_before.amount < amount {
+            return Err(ChrysalisError::InsufficientStake);
+        }
+        if from_before.amount - lock::locked_amount(&env, &from) < amount {
+            return Err(ChrysalisError::StakeLocked);
+        }
+        let from_weight = read_weight(&env, &from, &from_before);
+        let to_before: Stake = storage::read_stake(&env, &to);
+
+        // Settle both positions at the current index; their debts are reset for the new weights
+        let mut from_stake: Stake = update_stake(&env, &from, -amount);
+        let mut to_stake: Stake = update_stake(&env, &to, amount);
+
+        // The moved shares take the rewards they earned with them. Rewards accrue
+        // by weight, and the unlocked shares that move carry no boost, so they
+        // earned their weight's part of the sender's rewards
+        let rewards = from_stake.pending_rewards * amount / from_weight;
+        from_stake.pending_rewards -= rewards;
+        to_stake.pending_rewards += rewards;
+        reward_tokens::move_pending(&env, &from, &to, amount, from_weight);
+
+        // Merging keeps the amount-weighted age of both positions
+        to_stake.timestamp = ((to_before.amount * to_before.timestamp as i128
+            + amount * from_before.timestamp as i128)
+            / to_stake.amount) as u64;
+        storage::write_stake(&env, &from, &from_stake);
+        storage::write_stake(&env, &to, &to_stake);
+
+        events::stake_transferred(&env, &from, &to, amount, rewards, from_stake.amount, to_stake.amount);
+        Ok(())
+    }
+
+    fn claim(env: Env, caller: Address, user: Address) -> Result<i128, ChrysalisError> {
+        // Ensure the caller is the user or one of their operators
+        operator::require_caller(&env, &caller, &user, operator::OPERATOR_CLAIM)?;
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_CLAIMS)?;
+        claim_steth(&env, &user)
+    }
+
+    fn claim_all(env: Env, user: Address) -> Result<Vec<(Address, i128)>, ChrysalisError> {
+        user.require_auth();
+        storage::extend_instance(&env);
+        pause::require_not_paused(&env, pause::PAUSE_CLAIMS)?;
+
+        let mut paid = Vec::new(&env);
+        let rewards = claim_steth(&env, &user)?;
         if rewards > 0 {
-            // Transfer rewards (stETH) to the user
-            let steth_address: Address = env.storage().instance().get(&DataKey::StETHAddress).unwrap();
-            env.invoke_contract::<()>(
-                &steth_address,
-                &Symbol::new(&env, "transfer"),
-                Vec::from_array(
-                    &env,
-                    [
-                        env.current_contract_address().into_val(&env.clone()),  // From the contract
-                        user.into_val(&env.clone()),                             // To the user
-                        rewards.into_val(&env.clone()),
-                    ]
-                ),
-            );
-        
-            // Subtract the unstaked amount from the user's stake
-            stakes.amount -= rewards;
-            log!(&env, "Updated Balance: {}", stakes.amount);
-        
-            // Update storage with the new stake amount
-            env.storage().instance().set(&DataKey::Stake(user.clone()), &stakes);
+            paid.push_back((read_address(&env, &DataKey::StETHAddress)?, rewards));
+        }
+        let stakes: Stake = storage::read_stake(&env, &user);
+        for (token, amount) in reward_tokens::claim(&env, &user, read_weight(&env, &user, &stakes)).iter() {
+            events::reward_claimed(&env, &user, &token, amount);
+            paid.push_back((token, amount));
+        }
+        Ok(paid)
+    }
+
+    fn add_reward_token(env: Env, token: Address) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        // stETH is always paid through the primary emission
+        if token == read_address(&env, &DataKey::StETHAddress)? {
+            return Err(ChrysalisError::RewardTokenExists);
+        }
+        reward_tokens::add_token(&env, &token)?;
+        events::reward_token_added(&env, &token);
+        Ok(())
+    }
+
+    fn remove_reward_token(env: Env, token: Address) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        storage::extend_instance(&env);
+
+        let refund = reward_tokens::remove_token(&env, &token)?;
+        if refund > 0 {
+            token::Client::new(&env, &token).transfer(&env.current_contract_address(), &admin, &refund);
+        }
+        events::reward_token_removed(&env, &token, refund);
+        Ok(())
+    }
+
+    fn fund_reward_token(env: Env, from: Address, token: Address, amount: i128, duration: u64) -> Result<(), ChrysalisError> {
+        let admin = read_admin(&env)?;
+        admin.require_auth();
+        from.require_auth();
+        storage::extend_instance(&env);
+        if amount <= 0 || duration == 0 {
+            return Err(ChrysalisError::InvalidAmount);
+        }
+
+        let pool = reward_tokens::fund(&env, &token, amount, duration)?;
+        token::Client::new(&env, &token).transfer(&from, &env.current_contract_address(), &amount);
+        let stakes: Stake = storage::read_stake(&env, &from);
+        events::rewards_funded(&env, &from, amount, pool.rate, pool.period_finish, &token, stakes.amount);
+        Ok(())
+    }
+
+    fn get_reward_tokens(env: Env) -> Vec<Address> {
+        reward_tokens::read_tokens(&env)
+    }
+
+    fn get_reward_pool(env: Env, token: Address) -> Result<RewardPool, ChrysalisError> {
+        reward_tokens::read_pool(&env, &token)
+    }
+
+    fn pending_reward(env: Env, user: Address, token: Address) -> Result<i128, ChrysalisError> {
+        let stakes: Stake = storage::read_stake(&env, &user);
+        if token == read_address(&env, &DataKey::StETHAddress)? {
+            return Ok(Self::vclaim(env, user));
         }
-        rewards
+        reward_tokens::pending(&env, &user, &token, read_weight(&env, &user, &stakes))
     }
 
     // New Verify Claim Function
-    fn vclaim(env: Env, user: Address , apy: i128) -> i128 {
-        let stakes: Stake = env.storage().instance().get(&DataKey::Stake(user.clone())).unwrap_or_default();
+    fn vclaim(env: Env, user: Address) -> i128 {
+        let mut stakes: Stake = storage::read_stake(&env, &user);
 
         log!(&env, "Stake Amount: {}", stakes.amount);
-        // Assuming simple interest for rewards calculation:
-        let reward_rate: i128 = apy; // 5% reward rate per unit of time
-        let current_timestamp = env.ledger().timestamp();
-        let staking_duration = current_timestamp - stakes.timestamp;
-        log!(&env, "Staking Duration: {}", staking_duration , current_timestamp);
-        let rewards: i128 =  stakes.amount * reward_rate * staking_duration as i128 / 100 / 1_000_000;
-        rewards.into()
+        // Settle against the index as it would be now, without writing anything
+        let weight = read_weight(&env, &user, &stakes);
+        settle_rewards(&mut stakes, weight, current_reward_index(&env));
+        stakes.pending_rewards
     }   
 }
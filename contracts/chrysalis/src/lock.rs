@@ -0,0 +1,184 @@
+//! Time-locked stakes.
+//!
+//! `stake_locked` stakes like `stake_eth` but commits the minted shares until
+//! an unlock time, in exchange for a reward boost fixed when the lock is made.
+//! Locked shares remain part of the user's stake; they only count with a
+//! higher weight towards the reward emission and cannot be withdrawn early.
+//!
+//! Rewards are shared pro rata over `TotalWeight`, the sum of every stake's
+//! weight. An expired lock is dropped from the user's list, and from both the
+//! user's weight and `TotalWeight`, by `kick`, which anyone may call once the
+//! unlock time has passed. Claiming, compounding and bumping the stake drop
+//! expired locks as well.
+//!
+//! `early_unlock` releases a lock before its unlock time for a penalty that
+//! shrinks linearly with the time remaining, from `MAX_EARLY_UNLOCK_PENALTY`
+//! for the longest lock the boost curve allows down to nothing at unlock time.
+
+use soroban_sdk::{contracttype, Address, Env, Vec};
+
+use crate::error::ChrysalisError;
+use crate::storage;
+use crate::DataKey;
+
+/// Boost of an unlocked stake, in basis points.
+pub(crate) const BOOST_BASE: u32 = 10_000;
+/// Maximum number of locks a single user may hold at once.
+pub(crate) const MAX_LOCKS: u32 = 20;
+/// Share of the locked amount forfeited when unlocking `max_lock_duration`
+/// early, in basis points.
+pub(crate) const MAX_EARLY_UNLOCK_PENALTY: u32 = 5_000;
+const DEFAULT_MAX_BOOST: u32 = 25_000;
+const DEFAULT_MAX_LOCK_DURATION: u64 = 365 * 24 * 60 * 60;
+
+/// Linear boost curve from 1x for no lock up to `max_boost` for `max_lock_duration`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BoostCurve {
+    /// Boost of the longest lock, in basis points.
+    pub max_boost: u32,
+    /// Longest lock accepted, in seconds.
+    pub max_lock_duration: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Lock {
+    pub id: u64,
+    /// stETH shares committed by the lock.
+    pub amount: i128,
+    /// Ledger timestamp from which the shares can be withdrawn.
+    pub unlock_time: u64,
+    /// Reward boost of the locked shares, in basis points.
+    pub boost: u32,
+}
+
+pub(crate) fn read_curve(env: &Env) -> BoostCurve {
+    env.storage().instance().get(&DataKey::BoostCurve).unwrap_or(BoostCurve {
+        max_boost: DEFAULT_MAX_BOOST,
+        max_lock_duration: DEFAULT_MAX_LOCK_DURATION,
+    })
+}
+
+pub(crate) fn write_curve(env: &Env, curve: &BoostCurve) -> Result<(), ChrysalisError> {
+    if curve.max_boost < BOOST_BASE || curve.max_lock_duration == 0 {
+        return Err(ChrysalisError::InvalidAmount);
+    }
+    env.storage().instance().set(&DataKey::BoostCurve, curve);
+    Ok(())
+}
+
+/// Boost granted for locking over `duration`, in basis points.
+pub(crate) fn boost_for(env: &Env, duration: u64) -> Result<u32, ChrysalisError> {
+    let curve = read_curve(env);
+    if duration == 0 || duration > curve.max_lock_duration {
+        return Err(ChrysalisError::InvalidLockDuration);
+    }
+    let extra = (curve.max_boost - BOOST_BASE) as u64 * duration / curve.max_lock_duration;
+    Ok(BOOST_BASE + extra as u32)
+}
+
+pub(crate) fn read_locks(env: &Env, user: &Address) -> Vec<Lock> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Locks(user.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Writes the user's locks, leaving out the ones that have expired, and
+/// returns what was written.
+pub(crate) fn write_locks(env: &Env, user: &Address, locks: &Vec<Lock>) -> Vec<Lock> {
+    let now = env.ledger().timestamp();
+    let mut active = Vec::new(env);
+    for lock in locks.iter() {
+        if lock.unlock_time > now {
+            active.push_back(lock);
+        }
+    }
+
+    let key = DataKey::Locks(user.clone());
+    if active.is_empty() {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, &active);
+        storage::extend_persistent_until(env, &key, last_unlock_time(&active));
+    }
+    active
+}
+
+pub(crate) fn last_unlock_time(locks: &Vec<Lock>) -> u64 {
+    locks.iter().map(|lock| lock.unlock_time).max().unwrap_or(0)
+}
+
+/// Extends the user's locks, and the voting power entries that depend on them,
+/// until the last lock ends.
+pub(crate) fn bump_locks(env: &Env, user: &Address) {
+    let key = DataKey::Locks(user.clone());
+    if !env.storage().persistent().has(&key) {
+        return;
+    }
+    let locks = read_locks(env, user);
+    storage::extend_persistent_until(env, &key, last_unlock_time(&locks));
+    crate::ve::bump_user(env, user, &locks);
+    crate::delegation::bump(env, user, &locks);
+}
+
+/// Weight the locks add on top of the shares they hold.
+pub(crate) fn boost_weight(locks: &Vec<Lock>) -> i128 {
+    locks
+        .iter()
+        .map(|lock| lock.amount * (lock.boost - BOOST_BASE) as i128 / BOOST_BASE as i128)
+        .sum()
+}
+
+/// Shares that cannot be withdrawn yet.
+pub(crate) fn locked_amount(env: &Env, user: &Address) -> i128 {
+    let now = env.ledger().timestamp();
+    read_locks(env, user)
+        .iter()
+        .filter(|lock| lock.unlock_time > now)
+        .map(|lock| lock.amount)
+        .sum()
+}
+
+/// Appends a new lock of `amount` shares to `locks` and returns it.
+pub(crate) fn create_lock(
+    env: &Env,
+    locks: &mut Vec<Lock>,
+    amount: i128,
+    duration: u64,
+) -> Result<Lock, ChrysalisError> {
+    let boost = boost_for(env, duration)?;
+    let now = env.ledger().timestamp();
+    let active = locks.iter().filter(|lock| lock.unlock_time > now).count() as u32;
+    if active >= MAX_LOCKS {
+        return Err(ChrysalisError::TooManyLocks);
+    }
+
+    let id: u64 = env.storage().instance().get(&DataKey::NextLockId).unwrap_or(0);
+    env.storage().instance().set(&DataKey::NextLockId, &(id + 1));
+    let lock = Lock { id, amount, unlock_time: now + duration, boost };
+    locks.push_back(lock.clone());
+    Ok(lock)
+}
+
+/// Returns the position of the lock with `id` in `locks`.
+pub(crate) fn find_lock(locks: &Vec<Lock>, id: u64) -> Result<u32, ChrysalisError> {
+    locks
+        .iter()
+        .position(|lock| lock.id == id)
+        .map(|index| index as u32)
+        .ok_or(ChrysalisError::LockNotFound)
+}
+
+/// Shares forfeited for releasing `lock` now.
+pub(crate) fn early_unlock_penalty(env: &Env, lock: &Lock) -> i128 {
+    let now = env.ledger().timestamp();
+    if lock.unlock_time <= now {
+        return 0;
+    }
+    let max_lock_duration = read_curve(env).max_lock_duration;
+    let remaining = (lock.unlock_time - now).min(max_lock_duration);
+    lock.amount * MAX_EARLY_UNLOCK_PENALTY as i128 * remaining as i128
+        / (BOOST_BASE as i128 * max_lock_duration as i128)
+}
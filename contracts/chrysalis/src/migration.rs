@@ -0,0 +1,113 @@
+//! Storage schema versions and migrations.
+//!
+//! Schema version 1 is the original layout, where every `Stake { amount,
+//! timestamp }` was kept in instance storage. Version 2 keeps the extended
+//! `Stake` record in persistent storage. `migrate` moves version 1 records over
+//! in batches bounded by `MAX_MIGRATION_BATCH`, so a large staker set never has
+//! to fit into a single transaction.
+//!
+//! Version 1 handed stETH to stakers, while version 2 keeps staked shares in
+//! the contract's custody. A migrated record therefore does not become a stake:
+//! the stETH it was issued for stays with the user as held stETH, which they
+//! can stake again with `stake_steth`.
+//!
+//! Legacy records cannot be enumerated on chain, so `begin_migration` first
+//! records how many there are. Each migrated record counts down from there, and
+//! the schema version is only bumped once every legacy record has been moved.
+//!
+//! In-place migration of a deployed schema 1 contract is not supported. The
+//! original code has no `upgrade` entrypoint, so such an instance can never run
+//! this code, and it has no admin to authorize `begin_migration`, `migrate` or
+//! `restore_principal`, which `initialize_contract` cannot add to an instance
+//! that is already initialized. Stakers of such a deployment move by unstaking
+//! there and staking in a new one. The migration entrypoints only serve
+//! instances of this code that hold schema 1 records, such as test fixtures.
+
+use soroban_sdk::{contracttype, Address, Env, Vec};
+
+use crate::error::ChrysalisError;
+use crate::{pool, DataKey};
+
+/// Schema version written by this build of the contract.
+pub(crate) const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// Maximum number of users `migrate` processes in one call.
+pub(crate) const MAX_MIGRATION_BATCH: u32 = 25;
+
+/// Stake record as stored in instance storage by schema version 1.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct LegacyStake {
+    pub amount: i128,
+    pub timestamp: u64,
+}
+
+pub(crate) fn read_schema_version(env: &Env) -> u32 {
+    env.storage().instance().get(&DataKey::SchemaVersion).unwrap_or(1)
+}
+
+pub(crate) fn write_schema_version(env: &Env, version: u32) {
+    env.storage().instance().set(&DataKey::SchemaVersion, &version);
+}
+
+/// Legacy records not migrated yet, or `None` if no migration was started.
+pub(crate) fn read_legacy_remaining(env: &Env) -> Option<u32> {
+    env.storage().instance().get(&DataKey::LegacyRemaining)
+}
+
+/// Starts a migration of `legacy_count` legacy records.
+pub(crate) fn begin(env: &Env, legacy_count: u32) -> Result<(), ChrysalisError> {
+    if read_schema_version(env) >= CURRENT_SCHEMA_VERSION {
+        return Err(ChrysalisError::AlreadyMigrated);
+    }
+    if legacy_count == 0 {
+        return Err(ChrysalisError::InvalidAmount);
+    }
+    env.storage().instance().set(&DataKey::LegacyRemaining, &legacy_count);
+    Ok(())
+}
+
+/// Retires the legacy records of `users` and accounts for the stETH they were
+/// issued for in the pool. Users without a legacy record are skipped. Returns
+/// the number of records migrated.
+pub(crate) fn migrate_stakes(env: &Env, users: &Vec<Address>) -> Result<u32, ChrysalisError> {
+    if users.len() > MAX_MIGRATION_BATCH {
+        return Err(ChrysalisError::BatchTooLarge);
+    }
+    let remaining = read_legacy_remaining(env);
+    if remaining.is_none() && read_schema_version(env) < CURRENT_SCHEMA_VERSION {
+        return Err(ChrysalisError::MigrationNotStarted);
+    }
+
+    let mut migrated: u32 = 0;
+    let mut total_migrated: i128 = 0;
+    for user in users.iter() {
+        let key = DataKey::Stake(user.clone());
+        let legacy: LegacyStake = match env.storage().instance().get(&key) {
+            Some(legacy) => legacy,
+            None => continue,
+        };
+        env.storage().instance().remove(&key);
+        total_migrated += legacy.amount;
+        migrated += 1;
+    }
+
+    // Legacy stETH was handed out 1:1 against deposits that are still held by
+    // the contract, so each migrated unit is one share backed by one token
+    pool::write_totals(
+        env,
+        pool::read_total_pooled(env) + total_migrated,
+        pool::read_total_shares(env) + total_migrated,
+    );
+
+    if let Some(remaining) = remaining {
+        let remaining = remaining.saturating_sub(migrated);
+        if remaining == 0 {
+            env.storage().instance().remove(&DataKey::LegacyRemaining);
+            write_schema_version(env, CURRENT_SCHEMA_VERSION);
+        } else {
+            env.storage().instance().set(&DataKey::LegacyRemaining, &remaining);
+        }
+    }
+    Ok(migrated)
+}
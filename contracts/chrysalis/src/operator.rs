@@ -0,0 +1,116 @@
+//! Operator approvals.
+//!
+//! A staker can let another address manage their position, for instance a
+//! vault contract's keeper. Each approval grants a set of permission bits and
+//! lapses after an expiration ledger. Operators call `claim`, `compound` and
+//! `unstake_eth` with themselves as the caller, and whatever those pay out
+//! always goes to the staker, never to the operator.
+//!
+//! Staked shares are held by the contract, so an operator unstaking on the
+//! staker's behalf redeems them from the stake itself, never from stETH the
+//! staker holds. Each operator may only unstake the amount the staker approved
+//! for it.
+
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::error::ChrysalisError;
+use crate::storage;
+use crate::DataKey;
+
+/// Allows `claim` on the user's behalf.
+pub const OPERATOR_CLAIM: u32 = 1 << 0;
+/// Allows `compound` on the user's behalf.
+pub const OPERATOR_COMPOUND: u32 = 1 << 1;
+/// Allows `unstake_eth` on the user's behalf, which pays the staked token to the user.
+pub const OPERATOR_UNSTAKE: u32 = 1 << 2;
+
+pub const OPERATOR_ALL: u32 = OPERATOR_CLAIM | OPERATOR_COMPOUND | OPERATOR_UNSTAKE;
+
+#[contracttype]
+#[derive(Clone, Debug)]
+struct OperatorApproval {
+    permissions: u32,
+    /// Last ledger sequence in which the approval can be used.
+    expiration_ledger: u32,
+    /// Staked shares the operator may still unstake.
+    unstake_allowance: i128,
+}
+
+fn read_approval(env: &Env, user: &Address, operator: &Address) -> Option<OperatorApproval> {
+    env.storage().persistent().get(&DataKey::Operator(user.clone(), operator.clone()))
+}
+
+fn write_approval(env: &Env, user: &Address, operator: &Address, approval: &OperatorApproval) {
+    let key = DataKey::Operator(user.clone(), operator.clone());
+    env.storage().persistent().set(&key, approval);
+    storage::extend_persistent(env, &key);
+}
+
+/// Approves `operator` for `permissions` until `expiration_ledger`. With
+/// `OPERATOR_UNSTAKE`, the operator may unstake up to `unstake_allowance`
+/// staked shares; without it the allowance must be zero.
+pub(crate) fn approve(
+    env: &Env,
+    user: &Address,
+    operator: &Address,
+    permissions: u32,
+    expiration_ledger: u32,
+    unstake_allowance: i128,
+) -> Result<(), ChrysalisError> {
+    if permissions == 0 || permissions & !OPERATOR_ALL != 0 {
+        return Err(ChrysalisError::InvalidAmount);
+    }
+    let can_unstake = permissions & OPERATOR_UNSTAKE != 0;
+    if unstake_allowance < 0 || can_unstake != (unstake_allowance > 0) {
+        return Err(ChrysalisError::InvalidAmount);
+    }
+    if expiration_ledger < env.ledger().sequence() {
+        return Err(ChrysalisError::InvalidExpirationLedger);
+    }
+
+    write_approval(env, user, operator, &OperatorApproval { permissions, expiration_ledger, unstake_allowance });
+    Ok(())
+}
+
+/// Withdraws the operator's approval along with what is left of its unstake allowance.
+pub(crate) fn revoke(env: &Env, user: &Address, operator: &Address) {
+    env.storage().persistent().remove(&DataKey::Operator(user.clone(), operator.clone()));
+}
+
+/// Whether `operator` currently holds every permission in `permissions` for the user.
+pub(crate) fn is_approved(env: &Env, user: &Address, operator: &Address, permissions: u32) -> bool {
+    read_approval(env, user, operator).is_some_and(|approval| {
+        approval.expiration_ledger >= env.ledger().sequence() && approval.permissions & permissions == permissions
+    })
+}
+
+/// Checks that `caller` has authorized the call and is either the user or an
+/// operator allowed to act on the user's position with `permission`.
+pub(crate) fn require_caller(
+    env: &Env,
+    caller: &Address,
+    user: &Address,
+    permission: u32,
+) -> Result<(), ChrysalisError> {
+    caller.require_auth();
+    if caller != user && !is_approved(env, user, caller, permission) {
+        return Err(ChrysalisError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Takes `amount` out of what the operator may still unstake for the user.
+pub(crate) fn spend_unstake_allowance(
+    env: &Env,
+    user: &Address,
+    operator: &Address,
+    amount: i128,
+) -> Result<(), ChrysalisError> {
+    let mut approval = read_approval(env, user, operator).ok_or(ChrysalisError::Unauthorized)?;
+    if approval.unstake_allowance < amount {
+        return Err(ChrysalisError::AllowanceExceeded);
+    }
+    approval.unstake_allowance -= amount;
+    write_approval(env, user, operator, &approval);
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! Emergency pause flags.
+//!
+//! The paused state is a bit set so that deposits, withdrawals and claims can
+//! be halted independently. Both the admin and the guardian may flip the bits.
+
+use soroban_sdk::{Address, Env};
+
+use crate::error::ChrysalisError;
+use crate::DataKey;
+
+/// Halts `stake_eth` and yield distribution.
+pub const PAUSE_DEPOSITS: u32 = 1 << 0;
+/// Halts `unstake_eth` and the withdrawal queue.
+pub const PAUSE_WITHDRAWALS: u32 = 1 << 1;
+/// Halts `claim`.
+pub const PAUSE_CLAIMS: u32 = 1 << 2;
+
+pub const PAUSE_ALL: u32 = PAUSE_DEPOSITS | PAUSE_WITHDRAWALS | PAUSE_CLAIMS;
+
+pub(crate) fn read_paused(env: &Env) -> u32 {
+    env.storage().instance().get(&DataKey::Paused).unwrap_or(0)
+}
+
+pub(crate) fn write_paused(env: &Env, flags: u32) {
+    env.storage().instance().set(&DataKey::Paused, &flags);
+}
+
+pub(crate) fn read_guardian(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKey::Guardian)
+}
+
+/// Fails with `Paused` if any of `flags` is currently set.
+pub(crate) fn require_not_paused(env: &Env, flags: u32) -> Result<(), ChrysalisError> {
+    if read_paused(env) & flags != 0 {
+        return Err(ChrysalisError::Paused);
+    }
+    Ok(())
+}
+
+/// Checks that `caller` is the admin or the guardian and has authorized the call.
+pub(crate) fn require_pauser(env: &Env, caller: &Address) -> Result<(), ChrysalisError> {
+    caller.require_auth();
+    let admin: Address = env
+        .storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .ok_or(ChrysalisError::NotInitialized)?;
+    if *caller == admin || read_guardian(env).as_ref() == Some(caller) {
+        Ok(())
+    } else {
+        Err(ChrysalisError::Unauthorized)
+    }
+}
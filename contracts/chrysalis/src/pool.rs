@@ -0,0 +1,56 @@
+//! Exchange-rate accounting between the staked token and stETH shares.
+//!
+//! The pool holds `total_pooled` units of the staked token on behalf of
+//! `total_shares` units of stETH. Deposits mint shares at the current rate and
+//! redemptions pay out the proportional underlying, so yield added to the pool
+//! raises the value of every share instead of being paid out separately.
+
+use soroban_sdk::Env;
+
+use crate::DataKey;
+
+/// Fixed-point scale of the value returned by `get_exchange_rate`.
+pub(crate) const EXCHANGE_RATE_PRECISION: i128 = 1_000_000_000;
+
+pub(crate) fn read_total_pooled(env: &Env) -> i128 {
+    env.storage().instance().get(&DataKey::TotalPooled).unwrap_or(0)
+}
+
+pub(crate) fn read_total_shares(env: &Env) -> i128 {
+    env.storage().instance().get(&DataKey::TotalShares).unwrap_or(0)
+}
+
+pub(crate) fn write_totals(env: &Env, total_pooled: i128, total_shares: i128) {
+    env.storage().instance().set(&DataKey::TotalPooled, &total_pooled);
+    env.storage().instance().set(&DataKey::TotalShares, &total_shares);
+}
+
+/// Shares minted for depositing `amount` of the staked token, rounded down.
+pub(crate) fn shares_for_amount(env: &Env, amount: i128) -> i128 {
+    let total_pooled = read_total_pooled(env);
+    let total_shares = read_total_shares(env);
+    if total_shares == 0 || total_pooled == 0 {
+        return amount;
+    }
+    amount * total_shares / total_pooled
+}
+
+/// Staked token paid out for redeeming `shares`, rounded down.
+pub(crate) fn amount_for_shares(env: &Env, shares: i128) -> i128 {
+    let total_pooled = read_total_pooled(env);
+    let total_shares = read_total_shares(env);
+    if total_shares == 0 {
+        return 0;
+    }
+    shares * total_pooled / total_shares
+}
+
+/// Staked token backing one share, scaled by `EXCHANGE_RATE_PRECISION`.
+pub(crate) fn exchange_rate(env: &Env) -> i128 {
+    let total_pooled = read_total_pooled(env);
+    let total_shares = read_total_shares(env);
+    if total_shares == 0 {
+        return EXCHANGE_RATE_PRECISION;
+    }
+    total_pooled * EXCHANGE_RATE_PRECISION / total_shares
+}
@@ -0,0 +1,206 @@
+//! Additional reward tokens.
+//!
+//! Besides the stETH emission, the admin can register partner tokens that are
+//! streamed to stakers in proportion to their reward weight. Each token has its
+//! own pool with a rate, a reward index and a funded budget, and each staker
+//! has one persistent entry per token holding their debt against that index.
+//!
+//! Removing a token stops its emission and refunds the unspent budget, but the
+//! token stays in the registry so stakers can still claim what they earned.
+//! Adding it again resumes emission from the same index.
+
+use soroban_sdk::{contracttype, token, Address, Env, Vec};
+
+use crate::error::ChrysalisError;
+use crate::storage;
+use crate::{DataKey, REWARD_PRECISION};
+
+/// Maximum number of tokens, removed or not, that can be registered.
+pub(crate) const MAX_REWARD_TOKENS: u32 = 8;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RewardPool {
+    /// Amount of the token emitted per second while the period lasts.
+    pub rate: i128,
+    pub acc_reward_per_share: i128,
+    /// Funded rewards not yet emitted, as of `last_update`.
+    pub budget: i128,
+    pub period_finish: u64,
+    pub last_update: u64,
+    /// Whether the token is currently emitted. Removed tokens can still be claimed.
+    pub active: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Default)]
+struct UserReward {
+    reward_debt: i128,
+    pending: i128,
+}
+
+pub(crate) fn read_tokens(env: &Env) -> Vec<Address> {
+    env.storage().instance().get(&DataKey::RewardTokens).unwrap_or(Vec::new(env))
+}
+
+pub(crate) fn read_pool(env: &Env, token: &Address) -> Result<RewardPool, ChrysalisError> {
+    env.storage()
+        .instance()
+        .get(&DataKey::RewardPool(token.clone()))
+        .ok_or(ChrysalisError::RewardTokenNotFound)
+}
+
+fn write_pool(env: &Env, token: &Address, pool: &RewardPool) {
+    env.storage().instance().set(&DataKey::RewardPool(token.clone()), pool);
+}
+
+fn read_user_reward(env: &Env, user: &Address, token: &Address) -> UserReward {
+    env.storage()
+        .persistent()
+        .get(&DataKey::UserReward(user.clone(), token.clone()))
+        .unwrap_or_default()
+}
+
+fn write_user_reward(env: &Env, user: &Address, token: &Address, entry: &UserReward) {
+    let key = DataKey::UserReward(user.clone(), token.clone());
+    env.storage().persistent().set(&key, entry);
+    storage::extend_persistent(env, &key);
+}
+
+/// Returns the pool as it would be after accruing up to the current ledger
+/// timestamp, without writing it back.
+fn accrued(env: &Env, mut pool: RewardPool) -> RewardPool {
+    let end = env.ledger().timestamp().min(pool.period_finish);
+    let total_weight = crate::read_total_weight(env);
+    if end > pool.last_update && total_weight > 0 {
+        let emission = (pool.rate * (end - pool.last_update) as i128).min(pool.budget);
+        pool.acc_reward_per_share += emission * REWARD_PRECISION / total_weight;
+        pool.budget -= emission;
+    }
+    pool.last_update = pool.last_update.max(env.ledger().timestamp());
+    pool
+}
+
+fn update_pool(env: &Env, token: &Address) -> Result<RewardPool, ChrysalisError> {
+    let pool = accrued(env, read_pool(env, token)?);
+    write_pool(env, token, &pool);
+    Ok(pool)
+}
+
+/// Registers `token`, or resumes a removed one. Emission starts once it is funded.
+pub(crate) fn add_token(env: &Env, token: &Address) -> Result<(), ChrysalisError> {
+    let pool = match read_pool(env, token) {
+        Ok(pool) if pool.active => return Err(ChrysalisError::RewardTokenExists),
+        Ok(pool) => RewardPool { active: true, ..accrued(env, pool) },
+        Err(_) => {
+            let mut tokens = read_tokens(env);
+            if tokens.len() >= MAX_REWARD_TOKENS {
+                return Err(ChrysalisError::TooManyRewardTokens);
+            }
+            tokens.push_back(token.clone());
+            env.storage().instance().set(&DataKey::RewardTokens, &tokens);
+            RewardPool {
+                rate: 0,
+                acc_reward_per_share: 0,
+                budget: 0,
+                period_finish: env.ledger().timestamp(),
+                last_update: env.ledger().timestamp(),
+                active: true,
+            }
+        }
+    };
+    write_pool(env, token, &pool);
+    Ok(())
+}
+
+/// Stops emitting `token` and returns its unspent budget.
+pub(crate) fn remove_token(env: &Env, token: &Address) -> Result<i128, ChrysalisError> {
+    let mut pool = update_pool(env, token)?;
+    if !pool.active {
+        return Err(ChrysalisError::RewardTokenNotFound);
+    }
+    let refund = pool.budget;
+    pool.budget = 0;
+    pool.rate = 0;
+    pool.period_finish = env.ledger().timestamp();
+    pool.active = false;
+    write_pool(env, token, &pool);
+    Ok(refund)
+}
+
+/// Adds `amount` to the budget of `token` and spreads the whole unspent budget
+/// over `duration`. Returns the updated pool; the caller moves the tokens.
+pub(crate) fn fund(env: &Env, token: &Address, amount: i128, duration: u64) -> Result<RewardPool, ChrysalisError> {
+    let mut pool = update_pool(env, token)?;
+    if !pool.active {
+        return Err(ChrysalisError::RewardTokenNotFound);
+    }
+    pool.budget += amount;
+    pool.rate = pool.budget / duration as i128;
+    if pool.rate == 0 {
+        return Err(ChrysalisError::InvalidAmount);
+    }
+    pool.period_finish = env.ledger().timestamp() + duration;
+    write_pool(env, token, &pool);
+    Ok(pool)
+}
+
+/// Settles the user's rewards in every registered token for a weight of
+/// `old_weight`, then records their debt for a weight of `new_weight`.
+///
+/// Must run before `TotalWeight` is updated, so that emission up to now is
+/// shared over the weight it was earned by.
+pub(crate) fn update_user(env: &Env, user: &Address, old_weight: i128, new_weight: i128) {
+    for token in read_tokens(env).iter() {
+        let Ok(pool) = update_pool(env, &token) else { continue };
+        let mut entry = read_user_reward(env, user, &token);
+        if old_weight == 0 && new_weight == 0 && entry.pending == 0 {
+            continue;
+        }
+        entry.pending += old_weight * pool.acc_reward_per_share / REWARD_PRECISION - entry.reward_debt;
+        entry.reward_debt = new_weight * pool.acc_reward_per_share / REWARD_PRECISION;
+        write_user_reward(env, user, &token, &entry);
+    }
+}
+
+/// Moves the part of `from`'s settled rewards that `weight` of their
+/// `from_weight` earned over to `to`, in every registered token.
+pub(crate) fn move_pending(env: &Env, from: &Address, to: &Address, weight: i128, from_weight: i128) {
+    for token in read_tokens(env).iter() {
+        let mut from_entry = read_user_reward(env, from, &token);
+        let moved = from_entry.pending * weight / from_weight;
+        if moved == 0 {
+            continue;
+        }
+        from_entry.pending -= moved;
+        write_user_reward(env, from, &token, &from_entry);
+        let mut to_entry = read_user_reward(env, to, &token);
+        to_entry.pending += moved;
+        write_user_reward(env, to, &token, &to_entry);
+    }
+}
+
+/// Returns the user's unclaimed rewards in `token`, up to the current ledger timestamp.
+pub(crate) fn pending(env: &Env, user: &Address, token: &Address, weight: i128) -> Result<i128, ChrysalisError> {
+    let pool = accrued(env, read_pool(env, token)?);
+    let entry = read_user_reward(env, user, token);
+    Ok(entry.pending + weight * pool.acc_reward_per_share / REWARD_PRECISION - entry.reward_debt)
+}
+
+/// Pays out the user's rewards in every registered token and returns the
+/// amount paid per token, skipping tokens with nothing to pay.
+pub(crate) fn claim(env: &Env, user: &Address, weight: i128) -> Vec<(Address, i128)> {
+    update_user(env, user, weight, weight);
+    let mut paid = Vec::new(env);
+    for token in read_tokens(env).iter() {
+        let mut entry = read_user_reward(env, user, &token);
+        if entry.pending <= 0 {
+            continue;
+        }
+        token::Client::new(env, &token).transfer(&env.current_contract_address(), user, &entry.pending);
+        paid.push_back((token.clone(), entry.pending));
+        entry.pending = 0;
+        write_user_reward(env, user, &token, &entry);
+    }
+    paid
+}
@@ -0,0 +1,64 @@
+//! Solvency checks.
+//!
+//! The contract owes the staked token to stETH holders, and stETH to stakers:
+//! the shares it holds for their stakes, their unclaimed rewards and the
+//! rewards still to be emitted. A report compares both liabilities against the
+//! balances the contract actually holds, as reported by the token contracts.
+
+use soroban_sdk::{contracttype, Address, Env, IntoVal, Symbol};
+
+use crate::error::ChrysalisError;
+use crate::{pool, withdrawal, DataKey};
+
+/// Recorded liabilities of the contract against its actual token balances.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolvencyReport {
+    /// Staked token owed to stETH holders, including queued withdrawals.
+    pub staked_liability: i128,
+    /// Staked token held by the contract.
+    pub staked_balance: i128,
+    /// stETH held for stakes, owed as unclaimed rewards and the unspent reward
+    /// budget, plus the shares escrowed in the withdrawal queue.
+    pub steth_liability: i128,
+    /// stETH held by the contract.
+    pub steth_balance: i128,
+    /// Whether both balances cover their liabilities.
+    pub solvent: bool,
+}
+
+fn balance_of(env: &Env, token: &Address) -> i128 {
+    env.invoke_contract(
+        token,
+        &Symbol::new(env, "balance"),
+        (env.current_contract_address(),).into_val(env),
+    )
+}
+
+/// Builds a report from the current accounting and token balances.
+///
+/// Fails with `InvalidAccounting` if the escrow counter has gone negative, since
+/// no balance could make such a report meaningful.
+pub(crate) fn report(env: &Env) -> Result<SolvencyReport, ChrysalisError> {
+    let staked_token_address = crate::read_address(env, &DataKey::StakedTokenAddress)?;
+    let steth_address = crate::read_address(env, &DataKey::StETHAddress)?;
+
+    let staked_liability = pool::read_total_pooled(env);
+    let unclaimed_rewards = (crate::current_rewards_emitted(env) - crate::read_total_rewards_paid(env)).max(0);
+    let escrowed_shares = withdrawal::read_escrowed_shares(env);
+    if escrowed_shares < 0 {
+        return Err(ChrysalisError::InvalidAccounting);
+    }
+    let steth_liability =
+        crate::read_total_staked(env) + unclaimed_rewards + crate::reward_budget_remaining(env) + escrowed_shares;
+
+    let staked_balance = balance_of(env, &staked_token_address);
+    let steth_balance = balance_of(env, &steth_address);
+    Ok(SolvencyReport {
+        staked_liability,
+        staked_balance,
+        steth_liability,
+        steth_balance,
+        solvent: staked_balance >= staked_liability && steth_balance >= steth_liability,
+    })
+}
@@ -0,0 +1,85 @@
+//! Storage layout and TTL management.
+//!
+//! Contract-wide configuration and totals live in instance storage, which is
+//! loaded on every invocation and shares a single TTL with the contract. Each
+//! user's `Stake`, and every other per-user record, lives in its own persistent
+//! entry so that the instance does not grow with the number of stakers. Every
+//! entry is bumped whenever it is touched, and `bump_stake` lets anyone keep an
+//! idle position alive.
+//!
+//! Entries that must outlive an idle month, such as locks and the slope changes
+//! of their end weeks, are extended until the timestamp they are needed up to.
+
+use soroban_sdk::{Address, Env};
+
+use crate::{DataKey, Stake};
+
+const DAY_IN_LEDGERS: u32 = 17280;
+/// Expected ledger close time, used to turn timestamps into TTLs.
+const LEDGER_SECONDS: u64 = 5;
+
+pub(crate) const INSTANCE_BUMP_AMOUNT: u32 = 7 * DAY_IN_LEDGERS;
+pub(crate) const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+pub(crate) const PERSISTENT_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+pub(crate) const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+/// TTL of historical records, which must stay readable long after they were written.
+pub(crate) const HISTORY_BUMP_AMOUNT: u32 = 365 * DAY_IN_LEDGERS;
+
+pub(crate) fn extend_instance(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+pub(crate) fn has_stake(env: &Env, user: &Address) -> bool {
+    env.storage().persistent().has(&DataKey::Stake(user.clone()))
+}
+
+/// Returns the user's stake, or an empty one if they never staked.
+pub(crate) fn read_stake(env: &Env, user: &Address) -> Stake {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Stake(user.clone()))
+        .unwrap_or_default()
+}
+
+pub(crate) fn write_stake(env: &Env, user: &Address, stake: &Stake) {
+    let key = DataKey::Stake(user.clone());
+    env.storage().persistent().set(&key, stake);
+    bump_stake(env, user);
+}
+
+pub(crate) fn bump_stake(env: &Env, user: &Address) {
+    extend_persistent(env, &DataKey::Stake(user.clone()));
+}
+
+pub(crate) fn extend_persistent(env: &Env, key: &DataKey) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+}
+
+/// Extends a persistent entry so it lives at least until ledger timestamp
+/// `until`, and never for less than `PERSISTENT_BUMP_AMOUNT` ledgers. The TTL
+/// is capped by the network's maximum.
+pub(crate) fn extend_persistent_until(env: &Env, key: &DataKey, until: u64) {
+    let remaining = until.saturating_sub(env.ledger().timestamp()) / LEDGER_SECONDS;
+    let bump = u32::try_from(remaining)
+        .unwrap_or(u32::MAX)
+        .saturating_add(DAY_IN_LEDGERS)
+        .max(PERSISTENT_BUMP_AMOUNT)
+        .min(env.storage().max_ttl());
+    env.storage()
+        .persistent()
+        .extend_ttl(key, bump.saturating_sub(DAY_IN_LEDGERS), bump);
+}
+
+/// Extends a historical record to `HISTORY_BUMP_AMOUNT`, capped by the network's maximum.
+pub(crate) fn extend_history(env: &Env, key: &DataKey) {
+    let bump = HISTORY_BUMP_AMOUNT.min(env.storage().max_ttl());
+    env.storage()
+        .persistent()
+        .extend_ttl(key, bump.saturating_sub(DAY_IN_LEDGERS), bump);
+}
@@ -2,7 +2,8 @@
 extern crate std;
 
 use super::*;
-use soroban_sdk::testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, Ledger};
+use soroban_sdk::testutils::storage::Persistent as _;
+use soroban_sdk::testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, Events, Ledger};
 use soroban_sdk::{symbol_short, token, vec, Address, Env, IntoVal};
 use token::Client as TokenClient;
 use token::StellarAssetClient as TokenAdminClient;
@@ -15,32 +16,173 @@ fn create_token_contract<'a>(e: &Env, admin: &Address) -> (TokenClient<'a>, Toke
     )
 }
 
+/// Funds the stETH reward budget the way it is funded in production: a funder
+/// stakes the staked token and hands all of the minted stETH to the contract.
+fn fund_rewards_by_staking(
+    env: &Env,
+    client: &ChrysalisContractClient,
+    staked_client: &TokenAdminClient,
+    amount: i128,
+    duration: u64,
+) {
+    let funder = soroban_sdk::Address::generate(env);
+    staked_client.mint(&funder, &amount);
+    client.stake_eth(&funder, &amount);
+    client.fund_rewards(&funder, &amount, &duration);
+}
+
 fn create_claimable_balance_contract<'a>(e: &Env) -> ChrysalisContractClient<'a> {
     ChrysalisContractClient::new(e, &e.register_contract(None, ChrysalisContract {}))
 }
 
+#[test]
+fn test_initialize_requires_admin_auth() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let staked_token_address = soroban_sdk::Address::generate(&env);
+    let steth_address = soroban_sdk::Address::generate(&env);
+    let client = create_claimable_balance_contract(&env);
+
+    client.initialize_contract(&admin, &staked_token_address, &steth_address);
+
+    // The admin must have authorized the initialization
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            admin.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    client.address.clone(),
+                    Symbol::new(&env, "initialize_contract"),
+                    (admin.clone(), staked_token_address.clone(), steth_address.clone()).into_val(&env),
+                )),
+                sub_invocations: std::vec![],
+            }
+        )]
+    );
+    assert_eq!(client.get_admin(), admin);
+}
+
+#[test]
+fn test_initialize_twice() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let attacker = soroban_sdk::Address::generate(&env);
+    let token_address = soroban_sdk::Address::generate(&env);
+    let client = create_claimable_balance_contract(&env);
+
+    client.initialize_contract(&admin, &token_address, &token_address);
+    assert_eq!(
+        client.try_initialize_contract(&attacker, &token_address, &token_address),
+        Err(Ok(ChrysalisError::AlreadyInitialized))
+    );
+    assert_eq!(client.get_admin(), admin);
+}
+
+#[test]
+fn test_admin_transfer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let new_admin = soroban_sdk::Address::generate(&env);
+    let token_address = soroban_sdk::Address::generate(&env);
+    let client = create_claimable_balance_contract(&env);
+
+    client.initialize_contract(&admin, &token_address, &token_address);
+
+    // Proposing does not hand over control yet
+    client.propose_admin(&new_admin);
+    assert_eq!(client.get_admin(), admin);
+
+    // The proposed admin has to accept
+    client.accept_admin();
+    assert_eq!(env.auths()[0].0, new_admin);
+    assert_eq!(client.get_admin(), new_admin);
+}
+
+#[test]
+fn test_accept_admin_without_proposal() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let token_address = soroban_sdk::Address::generate(&env);
+    let client = create_claimable_balance_contract(&env);
+
+    client.initialize_contract(&admin, &token_address, &token_address);
+    assert_eq!(client.try_accept_admin(), Err(Ok(ChrysalisError::NoPendingAdmin)));
+}
+
+#[test]
+fn test_not_initialized() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let user_address = soroban_sdk::Address::generate(&env);
+    let client = create_claimable_balance_contract(&env);
+
+    assert_eq!(client.try_get_admin(), Err(Ok(ChrysalisError::NotInitialized)));
+    assert_eq!(
+        client.try_stake_eth(&user_address, &100),
+        Err(Ok(ChrysalisError::NotInitialized))
+    );
+}
+
+#[test]
+fn test_set_reward_rate() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let token_address = soroban_sdk::Address::generate(&env);
+    let client = create_claimable_balance_contract(&env);
+
+    client.initialize_contract(&admin, &token_address, &token_address);
+    assert_eq!(client.get_reward_rate(), 0);
+
+    client.set_reward_rate(&7);
+    assert_eq!(env.auths()[0].0, admin);
+    assert_eq!(client.get_reward_rate(), 7);
+
+    assert_eq!(client.try_set_reward_rate(&-1), Err(Ok(ChrysalisError::InvalidAmount)));
+}
+
+#[test]
+#[should_panic]
+fn test_set_reward_rate_requires_admin() {
+    let env = Env::default();
+    let admin = soroban_sdk::Address::generate(&env);
+    let token_address = soroban_sdk::Address::generate(&env);
+    let client = create_claimable_balance_contract(&env);
+
+    env.mock_all_auths();
+    client.initialize_contract(&admin, &token_address, &token_address);
+
+    // Without the admin's signature the rate cannot be changed
+    env.set_auths(&[]);
+    client.set_reward_rate(&1_000);
+}
+
 #[test]
 fn test_stake_eth() {
     let env = Env::default();
     env.mock_all_auths();
     let user_address = soroban_sdk::Address::generate(&env);
-    let (staked_token_address, staked_client) = create_token_contract(&env, &user_address);
+    let admin = soroban_sdk::Address::generate(&env);
+    let (_staked_token_address, staked_client) = create_token_contract(&env, &user_address);
     let (steth_address, steth_client) = create_token_contract(&env, &user_address);
 
     // Mint ETH to the user
     staked_client.mint(&user_address, &1000);
 
-    // Register the ChrysalisContract
+    // Register the ChrysalisContract and make it the stETH issuer
     let contract_id = env.register_contract(None, ChrysalisContract);
-
-    // Mint stETH to the contract
-    steth_client.mint(&contract_id, &1000);
+    steth_client.set_admin(&contract_id);
 
     // Create a client to interact with the contract
     let client = ChrysalisContractClient::new(&env, &contract_id);
 
     // Initialize the contract with staked_token_address and steth_address
-    client.initialize_contract(&staked_client.address, &steth_client.address);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
 
     // Call stake_eth
     client.stake_eth(&user_address, &500);
@@ -48,15 +190,13 @@ fn test_stake_eth() {
     // Verify staking
     let stake_key = DataKey::Stake(user_address.clone());
     let stake = env.as_contract(&contract_id, || {
-        env.storage().instance().get::<DataKey, Stake>(&stake_key).unwrap()
+        env.storage().persistent().get::<DataKey, Stake>(&stake_key).unwrap()
     });
     assert_eq!(stake.amount, 500);
 
-    // Check that 500 stETH was transferred to the user
+    // The 500 stETH minted for the stake is held by the contract
     let steth_balance = steth_address.balance(&user_address);
-    assert_eq!(steth_balance, 500);
-
-    // Check that 500 stETH was deducted from the contract
+    assert_eq!(steth_balance, 0);
     let contract_steth_balance = steth_address.balance(&contract_id);
     assert_eq!(contract_steth_balance, 500);
 }
@@ -66,45 +206,238 @@ fn test_unstake_eth() {
     let env = Env::default();
     env.mock_all_auths();
     let user_address = soroban_sdk::Address::generate(&env);
+    let admin = soroban_sdk::Address::generate(&env);
     let (staked_token_address, staked_client) = create_token_contract(&env, &user_address);
     let (steth_address, steth_client) = create_token_contract(&env, &user_address);
 
     // Mint ETH to the user
     staked_client.mint(&user_address, &1000);
 
-    // Register the ChrysalisContract
+    // Register the ChrysalisContract and make it the stETH issuer
     let contract_id = env.register_contract(None, ChrysalisContract);
-
-    // Mint stETH to the contract
-    steth_client.mint(&contract_id, &1000);
+    steth_client.set_admin(&contract_id);
 
     // Create a client to interact with the contract
     let client = ChrysalisContractClient::new(&env, &contract_id);
 
     // Initialize the contract
-    client.initialize_contract(&staked_client.address, &steth_client.address);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
 
     // Stake 500 ETH
     client.stake_eth(&user_address, &500);
 
-    // Unstake 250 stETH by transferring it back to the contract
-    // steth_address.transfer(&user_address, &contract_id, &250);
-    client.unstake_eth(&user_address, &250);
+    // Unstake 250 stETH, which burns it
+    client.unstake_eth(&user_address, &user_address, &250);
 
     // Verify the new stake amount is 250
     let stake_key = DataKey::Stake(user_address.clone());
     let stake = env.as_contract(&contract_id, || {
-        env.storage().instance().get::<DataKey, Stake>(&stake_key).unwrap()
+        env.storage().persistent().get::<DataKey, Stake>(&stake_key).unwrap()
     });
     assert_eq!(stake.amount, 250);
 
-    // Verify the user's stETH balance is 250
-    let steth_balance = steth_address.balance(&user_address);
-    assert_eq!(steth_balance, 250);
-
     // Verify the user's ETH balance has increased by 250
     let eth_balance = staked_token_address.balance(&user_address);
     assert_eq!(eth_balance, 750);
+
+    // stETH supply tracks outstanding stake: the contract holds the remaining 250
+    assert_eq!(steth_address.balance(&user_address), 0);
+    assert_eq!(steth_address.balance(&contract_id), 250);
+}
+
+#[test]
+fn test_exchange_rate() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &1000);
+    staked_client.mint(&bob, &300);
+    staked_client.mint(&admin, &500);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    // The first deposit mints shares 1:1
+    assert_eq!(client.get_exchange_rate(), pool::EXCHANGE_RATE_PRECISION);
+    client.stake_eth(&alice, &1000);
+    assert_eq!(client.get_stake_amount(&alice), 1000);
+
+    // Yield raises the value of every share
+    client.distribute_yield(&admin, &500);
+    assert_eq!(client.get_exchange_rate(), pool::EXCHANGE_RATE_PRECISION * 3 / 2);
+
+    // Later deposits get fewer shares for the same amount
+    assert_eq!(client.preview_stake(&300), 200);
+    client.stake_eth(&bob, &300);
+    assert_eq!(client.get_stake_amount(&bob), 200);
+    assert_eq!(steth_token.balance(&contract_id), 1200);
+
+    // Redemptions pay out the proportional underlying
+    assert_eq!(client.preview_unstake(&1000), 1500);
+    client.unstake_eth(&alice, &alice, &1000);
+    assert_eq!(staked_token.balance(&alice), 1500);
+    assert_eq!(steth_token.balance(&contract_id), 200);
+
+    client.unstake_eth(&bob, &bob, &200);
+    assert_eq!(staked_token.balance(&bob), 300);
+    assert_eq!(staked_token.balance(&contract_id), 0);
+}
+
+#[test]
+fn test_distribute_yield_without_stakers() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&admin, &500);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    assert_eq!(client.try_distribute_yield(&admin, &500), Err(Ok(ChrysalisError::NoStake)));
+    assert_eq!(client.try_distribute_yield(&admin, &0), Err(Ok(ChrysalisError::InvalidAmount)));
+}
+
+#[test]
+fn test_bump_stake() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let user_address = soroban_sdk::Address::generate(&env);
+    let keeper = soroban_sdk::Address::generate(&env);
+    let admin = soroban_sdk::Address::generate(&env);
+    let (_staked_token_address, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_address, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&user_address, &100);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    assert_eq!(client.try_bump_stake(&keeper), Err(Ok(ChrysalisError::NoStake)));
+
+    client.stake_eth(&user_address, &100);
+    let stake_key = DataKey::Stake(user_address.clone());
+    let ttl = env.as_contract(&contract_id, || env.storage().persistent().get_ttl(&stake_key));
+    assert_eq!(ttl, storage::PERSISTENT_BUMP_AMOUNT);
+
+    // Once the entry is close to expiring anyone can extend it again
+    env.ledger().with_mut(|li| li.sequence_number += 2 * 17280);
+    let ttl = env.as_contract(&contract_id, || env.storage().persistent().get_ttl(&stake_key));
+    assert!(ttl < storage::PERSISTENT_LIFETIME_THRESHOLD);
+    client.bump_stake(&user_address);
+    let ttl = env.as_contract(&contract_id, || env.storage().persistent().get_ttl(&stake_key));
+    assert_eq!(ttl, storage::PERSISTENT_BUMP_AMOUNT);
+    assert_eq!(client.get_stake_amount(&user_address), 100);
+}
+
+#[test]
+fn test_stake_and_unstake_errors() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let user_address = soroban_sdk::Address::generate(&env);
+    let admin = soroban_sdk::Address::generate(&env);
+    let (_staked_token_address, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_address, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&user_address, &100);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    assert_eq!(client.try_stake_eth(&user_address, &0), Err(Ok(ChrysalisError::InvalidAmount)));
+    assert_eq!(
+        client.try_stake_eth(&user_address, &101),
+        Err(Ok(ChrysalisError::InsufficientBalance))
+    );
+
+    client.stake_eth(&user_address, &100);
+    assert_eq!(client.try_unstake_eth(&user_address, &user_address, &-5), Err(Ok(ChrysalisError::InvalidAmount)));
+    assert_eq!(
+        client.try_unstake_eth(&user_address, &user_address, &101),
+        Err(Ok(ChrysalisError::InsufficientStake))
+    );
+}
+
+#[test]
+fn test_stake_unstake_claim_events() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let user_address = soroban_sdk::Address::generate(&env);
+    let admin = soroban_sdk::Address::generate(&env);
+    let (_staked_token_address, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_address, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&user_address, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("init"),).into_val(&env),
+                (events::EVENT_VERSION, admin.clone(), staked_client.address.clone(), steth_client.address.clone(), 0u64).into_val(&env),
+            ),
+        ]
+    );
+    fund_rewards_by_staking(&env, &client, &staked_client, 2000, 2000);
+    client.set_reward_rate(&1);
+
+    env.ledger().set_timestamp(10);
+    client.stake_eth(&user_address, &500);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("stake"), user_address.clone()).into_val(&env),
+                (events::EVENT_VERSION, 500i128, 500i128, 10u64, 500i128, user_address.clone()).into_val(&env),
+            ),
+        ]
+    );
+
+    env.ledger().set_timestamp(20);
+    client.unstake_eth(&user_address, &user_address, &250);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("unstake"), user_address.clone()).into_val(&env),
+                (events::EVENT_VERSION, 250i128, 250i128, 20u64, 250i128).into_val(&env),
+            ),
+        ]
+    );
+
+    env.ledger().set_timestamp(30);
+    client.claim(&user_address, &user_address);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("claim"), user_address.clone()).into_val(&env),
+                (events::EVENT_VERSION, 20i128, 250i128, 30u64).into_val(&env),
+            ),
+        ]
+    );
 }
 
 #[test]
@@ -113,68 +446,65 @@ fn test_claim() {
     env.mock_all_auths();
     
     let user_address = soroban_sdk::Address::generate(&env);
-    let (staked_token_address, staked_client) = create_token_contract(&env, &user_address);
+    let admin = soroban_sdk::Address::generate(&env);
+    let (_staked_token_address, staked_client) = create_token_contract(&env, &user_address);
     let (steth_address, steth_client) = create_token_contract(&env, &user_address);
 
+    // Mint ETH to the user
+    let initial_amount: i128 = 1000;
+    staked_client.mint(&user_address, &initial_amount);
+
     // Register the ChrysalisContract
     let contract_id = env.register_contract(None, ChrysalisContract);
 
-    // Mint stETH to the contract to ensure it can distribute rewards
-    steth_client.mint(&contract_id, &1000);
+    // Hand stETH issuance to Chrysalis
+    steth_client.set_admin(&contract_id);
 
     // Create a client to interact with the contract
     let client = ChrysalisContractClient::new(&env, &contract_id);
 
-    // Set the stETH address in the contract's storage
-    env.as_contract(&contract_id, || {
-        env.storage().instance().set(&DataKey::StETHAddress, &steth_client.address);
-    });
-
     // Initialize the contract
-    client.initialize_contract(&staked_client.address, &steth_client.address);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 2000, 2000);
+    client.set_reward_rate(&5);
 
-    // Simulate a stake with a specific timestamp
-    let initial_amount: i128 = 1000;
-    let initial_timestamp = 0;
-    env.as_contract(&contract_id, || {
-        env.storage().instance().set(
-            &DataKey::Stake(user_address.clone()),
-            &Stake {
-                amount: initial_amount,
-                timestamp: initial_timestamp,
-            },
-        );
-    });
+    // Stake at timestamp 0
+    client.stake_eth(&user_address, &initial_amount);
 
-    // Simulate time passage (e.g., 1,000,000 time units)
-    let elapsed_time: u64 = 1_000_000;
+    // Simulate time passage
+    let elapsed_time: u64 = 100;
     env.ledger().set_timestamp(elapsed_time);
 
     // Call the claim function
-    let claimed_rewards = client.claim(&user_address , &5);
+    let claimed_rewards = client.claim(&user_address, &user_address);
 
-    // Calculate expected rewards manually: (amount * reward_rate * duration / 100 / 1_000_000)
-    let reward_rate = 5; // 5% per unit of time
-    let expected_rewards = (initial_amount * reward_rate * elapsed_time as i128) / 100 / 1_000_000;
+    // A single staker receives the whole emission: reward_rate * duration
+    let reward_rate = 5;
+    let expected_rewards = reward_rate * elapsed_time as i128;
 
     // Log and assert the correct reward amount
     log!(&env, "Claimed Rewards: {}", claimed_rewards);
     assert_eq!(claimed_rewards, expected_rewards);
 
-    // Check that the user received the correct rewards in stETH
+    // Check that the user received the correct rewards in stETH; the staked
+    // shares stay with the contract
     let final_balance = steth_address.balance(&user_address);
     log!(&env, "Final stETH balance for user: {}", final_balance);
     assert_eq!(final_balance, expected_rewards);
 
-    // Ensure that the stake's timestamp was updated correctly
     let stake_key = DataKey::Stake(user_address.clone());
     let updated_stake: Stake = env.as_contract(&contract_id, || {
-        env.storage().instance().get::<DataKey, Stake>(&stake_key).unwrap()
+        env.storage().persistent().get::<DataKey, Stake>(&stake_key).unwrap()
     });
-    // assert_eq!(updated_stake.timestamp, elapsed_time);
 
-    // Ensure that the stake amount is updated after claiming rewards
-    assert_eq!(updated_stake.amount, initial_amount - claimed_rewards);
+    // Claiming rewards leaves the principal untouched
+    assert_eq!(updated_stake.amount, initial_amount);
+    assert_eq!(updated_stake.pending_rewards, 0);
+    assert_eq!(updated_stake.claimed_total, claimed_rewards);
+    assert_eq!(client.get_stake_amount(&user_address), initial_amount);
+
+    // Nothing more to claim in the same ledger
+    assert_eq!(client.claim(&user_address, &user_address), 0);
 }
 
 
@@ -184,51 +514,1592 @@ fn test_vclaim() {
     let env = Env::default();
     env.mock_all_auths();
     let user_address = soroban_sdk::Address::generate(&env);
-    let (staked_token_address , staked_client) = create_token_contract(&env, &user_address);
-    let (steth_address , steth_client) = create_token_contract(&env, &user_address);
-    // staked_client.mint(&user_address, &1000);
-    
+    let admin = soroban_sdk::Address::generate(&env);
+    let (_staked_token_address , staked_client) = create_token_contract(&env, &user_address);
+    let (_steth_address , steth_client) = create_token_contract(&env, &user_address);
+    staked_client.mint(&user_address, &1000);
 
     // Register the ChrysalisContract
     let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
 
     // Create a client to interact with the contract
     let client = ChrysalisContractClient::new(&env, &contract_id);
 
     // Initialize the contract directly through the client
-    client.initialize_contract(&staked_client.address, &staked_client.address);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 2000, 2000);
+    client.set_reward_rate(&5);
 
     let initial_amount: i128 = 1000;
-    let initial_timestamp = 0;
-    env.as_contract(&contract_id, || {
-        env.storage().instance().set(
-            &DataKey::Stake(user_address.clone()), 
-            &Stake { amount: initial_amount, timestamp: initial_timestamp }
-        );
-    });
+    client.stake_eth(&user_address, &initial_amount);
 
-    // Simulate some time passage (e.g., 1,000,000 time units)
-    let elapsed_time: u64 = 1_000_000; // 1 second in terms of microseconds
+    // Simulate some time passage
+    let elapsed_time: u64 = 100;
     env.ledger().set_timestamp(elapsed_time);
 
-    // Call the claim function and calculate rewards
-    let claimed_rewards = client.vclaim(&user_address , &5);
-    log!(&env, "Claimed Rewards: {}", claimed_rewards);
+    // Preview the rewards
+    let claimed_rewards = client.vclaim(&user_address);
 
-    // Check the final stake to ensure the timestamp was updated
+    // Previewing must not settle anything
     let stake_key = DataKey::Stake(user_address.clone());
-    // let updated_stake: Stake = env.as_contract(&contract_id, || {
-    //     env.storage().instance().get::<DataKey, Stake>(&stake_key).unwrap()
-    // });
-
-    // Validate that the timestamp has been updated
-    // assert_eq!(updated_stake.timestamp, elapsed_time);
+    let stake: Stake = env.as_contract(&contract_id, || {
+        env.storage().persistent().get::<DataKey, Stake>(&stake_key).unwrap()
+    });
+    assert_eq!(stake.pending_rewards, 0);
 
-    // Calculate expected rewards manually: reward_rate * amount * duration / 1_000_000
-    let reward_rate: i128 = 5; // 5% per unit of time
-    let expected_rewards: i128 = (initial_amount * reward_rate * elapsed_time as i128) / 100 / 1_000_000;
+    // A single staker receives the whole emission: reward_rate * duration
+    let reward_rate: i128 = 5;
+    let expected_rewards: i128 = reward_rate * elapsed_time as i128;
 
     // Log and assert the correct reward amount
     log!(&env, "Claimed Rewards: {}", claimed_rewards , expected_rewards);
     assert_eq!(claimed_rewards, expected_rewards);
-}
\ No newline at end of file
+    assert_eq!(client.claim(&user_address, &user_address), expected_rewards);
+}
+
+#[test]
+fn test_rewards_split_between_stakers() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (_staked_token_address, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_address, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &1000);
+    staked_client.mint(&bob, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 10_000, 10_000);
+    client.set_reward_rate(&10);
+
+    // Alice is alone for the first 100 seconds
+    client.stake_eth(&alice, &250);
+    env.ledger().set_timestamp(100);
+
+    // Bob joins with three times Alice's stake
+    client.stake_eth(&bob, &750);
+    env.ledger().set_timestamp(200);
+
+    assert_eq!(client.vclaim(&alice), 1000 + 250);
+    assert_eq!(client.vclaim(&bob), 750);
+}
+
+#[test]
+fn test_reward_rate_change_is_not_retroactive() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let user_address = soroban_sdk::Address::generate(&env);
+    let (_staked_token_address, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_address, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&user_address, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 10_000, 10_000);
+    client.set_reward_rate(&1);
+
+    client.stake_eth(&user_address, &1000);
+    env.ledger().set_timestamp(100);
+
+    // Raising the rate only affects the time after the change
+    client.set_reward_rate(&20);
+    assert_eq!(client.vclaim(&user_address), 100);
+
+    env.ledger().set_timestamp(110);
+    assert_eq!(client.vclaim(&user_address), 100 + 200);
+}
+
+#[test]
+fn test_restore_principal() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let user_address = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&user_address, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    client.stake_eth(&user_address, &1000);
+
+    // Simulate a record that an earlier version reduced by 40 claimed rewards
+    let stake_key = DataKey::Stake(user_address.clone());
+    env.as_contract(&contract_id, || {
+        let mut stake: Stake = env.storage().persistent().get(&stake_key).unwrap();
+        stake.amount -= 40;
+        env.storage().persistent().set(&stake_key, &stake);
+        env.storage().instance().set(&DataKey::TotalStaked, &960i128);
+    });
+
+    // The principal can neither shrink nor exceed the shares the contract holds for nobody
+    assert_eq!(client.try_restore_principal(&user_address, &900), Err(Ok(ChrysalisError::InvalidAmount)));
+    assert_eq!(client.try_restore_principal(&user_address, &1001), Err(Ok(ChrysalisError::InvalidAmount)));
+
+    client.restore_principal(&user_address, &1000);
+    assert_eq!(client.get_stake_amount(&user_address), 1000);
+
+    // The restored position can be fully withdrawn again
+    client.unstake_eth(&user_address, &user_address, &1000);
+    assert_eq!(client.get_stake_amount(&user_address), 0);
+}
+
+#[test]
+fn test_withdrawal_queue() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let user_address = soroban_sdk::Address::generate(&env);
+    let other = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&user_address, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    client.set_unbonding_period(&100);
+    client.stake_eth(&user_address, &1000);
+
+    // Immediate withdrawals are disabled while unbonding is required
+    assert_eq!(
+        client.try_unstake_eth(&user_address, &user_address, &100),
+        Err(Ok(ChrysalisError::UnbondingRequired))
+    );
+
+    env.ledger().set_timestamp(10);
+    let first = client.request_unstake(&user_address, &300);
+    let second = client.request_unstake(&user_address, &200);
+    assert_eq!(client.get_stake_amount(&user_address), 500);
+    assert_eq!(steth_token.balance(&contract_id), 1000);
+
+    let requests = client.get_withdrawal_requests(&user_address);
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests.get(0).unwrap(),
+        WithdrawalRequest { id: first, owner: user_address.clone(), shares: 300, stake_shares: 300, unlock_time: 110 }
+    );
+
+    // Requests cannot be claimed early or by someone else
+    assert_eq!(
+        client.try_claim_withdrawal(&user_address, &first),
+        Err(Ok(ChrysalisError::WithdrawalNotReady))
+    );
+    env.ledger().set_timestamp(110);
+    assert_eq!(
+        client.try_claim_withdrawal(&other, &first),
+        Err(Ok(ChrysalisError::Unauthorized))
+    );
+
+    assert_eq!(client.claim_withdrawal(&user_address, &first), 300);
+    assert_eq!(staked_token.balance(&user_address), 300);
+    assert_eq!(steth_token.balance(&contract_id), 700);
+    assert_eq!(
+        client.try_claim_withdrawal(&user_address, &first),
+        Err(Ok(ChrysalisError::WithdrawalNotFound))
+    );
+
+    // Cancelling puts the shares back into the stake
+    client.cancel_withdrawal(&user_address, &second);
+    assert_eq!(client.get_stake_amount(&user_address), 700);
+    assert_eq!(steth_token.balance(&user_address), 0);
+    assert_eq!(client.get_withdrawal_requests(&user_address).len(), 0);
+
+    // stETH held outside the stake is escrowed first, and does not become stake on cancel
+    steth_client.mint(&user_address, &100);
+    let third = client.request_unstake(&user_address, &300);
+    assert_eq!(client.get_stake_amount(&user_address), 500);
+    client.cancel_withdrawal(&user_address, &third);
+    assert_eq!(client.get_stake_amount(&user_address), 700);
+    assert_eq!(client.get_total_staked(), 700);
+    assert_eq!(steth_token.balance(&user_address), 100);
+    assert_eq!(steth_token.balance(&contract_id), 700);
+}
+
+#[test]
+fn test_withdrawal_request_stops_rewards() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &500);
+    staked_client.mint(&bob, &500);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 10_000, 10_000);
+    client.set_reward_rate(&10);
+    client.set_unbonding_period(&1000);
+    client.stake_eth(&alice, &500);
+    client.stake_eth(&bob, &500);
+
+    env.ledger().set_timestamp(100);
+    client.request_unstake(&alice, &500);
+
+    // Bob earns the whole emission while Alice's shares sit in the queue
+    env.ledger().set_timestamp(200);
+    assert_eq!(client.vclaim(&alice), 500);
+    assert_eq!(client.vclaim(&bob), 500 + 1000);
+}
+
+#[test]
+fn test_pause_gates() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let guardian = soroban_sdk::Address::generate(&env);
+    let user_address = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&user_address, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 1000, 1000);
+    client.set_reward_rate(&1);
+    client.set_guardian(&guardian);
+    client.stake_eth(&user_address, &500);
+    env.ledger().set_timestamp(10);
+
+    // Deposits
+    client.pause(&guardian, &pause::PAUSE_DEPOSITS);
+    assert_eq!(client.get_paused(), pause::PAUSE_DEPOSITS);
+    assert_eq!(client.try_stake_eth(&user_address, &100), Err(Ok(ChrysalisError::Paused)));
+    client.unstake_eth(&user_address, &user_address, &100);
+    client.claim(&user_address, &user_address);
+    client.unpause(&guardian, &pause::PAUSE_DEPOSITS);
+    client.stake_eth(&user_address, &100);
+
+    // Withdrawals
+    client.pause(&admin, &pause::PAUSE_WITHDRAWALS);
+    assert_eq!(client.try_unstake_eth(&user_address, &user_address, &100), Err(Ok(ChrysalisError::Paused)));
+    assert_eq!(client.try_request_unstake(&user_address, &100), Err(Ok(ChrysalisError::Paused)));
+    client.stake_eth(&user_address, &100);
+    client.unpause(&admin, &pause::PAUSE_WITHDRAWALS);
+    client.unstake_eth(&user_address, &user_address, &100);
+
+    // Claims
+    client.pause(&guardian, &pause::PAUSE_CLAIMS);
+    assert_eq!(client.try_claim(&user_address, &user_address), Err(Ok(ChrysalisError::Paused)));
+    client.unpause(&guardian, &pause::PAUSE_CLAIMS);
+    client.claim(&user_address, &user_address);
+    assert_eq!(client.get_paused(), 0);
+}
+
+#[test]
+fn test_pause_restricted_to_admin_and_guardian() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let guardian = soroban_sdk::Address::generate(&env);
+    let stranger = soroban_sdk::Address::generate(&env);
+    let token_address = soroban_sdk::Address::generate(&env);
+    let client = create_claimable_balance_contract(&env);
+
+    client.initialize_contract(&admin, &token_address, &token_address);
+    assert_eq!(client.get_guardian(), None);
+    assert_eq!(
+        client.try_pause(&guardian, &pause::PAUSE_ALL),
+        Err(Ok(ChrysalisError::Unauthorized))
+    );
+
+    client.set_guardian(&guardian);
+    assert_eq!(client.get_guardian(), Some(guardian.clone()));
+    client.pause(&guardian, &pause::PAUSE_ALL);
+    assert_eq!(client.get_paused(), pause::PAUSE_ALL);
+    assert_eq!(
+        client.try_unpause(&stranger, &pause::PAUSE_ALL),
+        Err(Ok(ChrysalisError::Unauthorized))
+    );
+    client.unpause(&admin, &pause::PAUSE_CLAIMS);
+    assert_eq!(client.get_paused(), pause::PAUSE_DEPOSITS | pause::PAUSE_WITHDRAWALS);
+}
+
+#[test]
+fn test_migrate_legacy_stakes() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let carol = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    assert_eq!(client.version(), migration::CURRENT_SCHEMA_VERSION);
+
+    // Simulate records, deposits and the stETH handed out under schema version 1.
+    // A real schema 1 deployment cannot be upgraded to this code and has no admin,
+    // so the state is written into an instance of this code instead
+    staked_client.mint(&contract_id, &500);
+    steth_client.mint(&alice, &300);
+    steth_client.mint(&bob, &200);
+    env.as_contract(&contract_id, || {
+        env.storage().instance().remove(&DataKey::SchemaVersion);
+        env.storage().instance().set(
+            &DataKey::Stake(alice.clone()),
+            &migration::LegacyStake { amount: 300, timestamp: 5 },
+        );
+        env.storage().instance().set(
+            &DataKey::Stake(bob.clone()),
+            &migration::LegacyStake { amount: 200, timestamp: 7 },
+        );
+    });
+    assert_eq!(client.version(), 1);
+    assert_eq!(client.get_stake_amount(&alice), 0);
+
+    // Batches are bounded
+    let mut too_many = vec![&env];
+    for _ in 0..migration::MAX_MIGRATION_BATCH + 1 {
+        too_many.push_back(soroban_sdk::Address::generate(&env));
+    }
+    assert_eq!(client.try_migrate(&too_many), Err(Ok(ChrysalisError::BatchTooLarge)));
+
+    // The legacy records must be counted before any batch runs
+    assert_eq!(
+        client.try_migrate(&vec![&env, alice.clone()]),
+        Err(Ok(ChrysalisError::MigrationNotStarted))
+    );
+    client.begin_migration(&2);
+
+    // Users without a legacy record are skipped, and reruns are harmless
+    assert_eq!(client.migrate(&vec![&env, alice.clone(), carol.clone()]), 1);
+    assert_eq!(client.migrate(&vec![&env, alice.clone()]), 0);
+    // Bob is not migrated yet, so the schema is still out of date
+    assert_eq!(client.version(), 1);
+    assert_eq!(client.get_legacy_remaining(), 1);
+    assert_eq!(client.migrate(&vec![&env, alice.clone(), bob.clone()]), 1);
+    assert_eq!(client.version(), migration::CURRENT_SCHEMA_VERSION);
+    assert_eq!(client.get_legacy_remaining(), 0);
+    assert_eq!(client.try_begin_migration(&1), Err(Ok(ChrysalisError::AlreadyMigrated)));
+    env.as_contract(&contract_id, || {
+        assert!(!env.storage().instance().has(&DataKey::Stake(alice.clone())));
+    });
+
+    // Legacy stETH stays with its holders until they stake it into custody
+    assert_eq!(client.get_total_pooled(), 500);
+    assert_eq!(client.get_stake_amount(&alice), 0);
+    client.stake_steth(&alice, &300);
+    assert_eq!(client.get_stake_amount(&alice), 300);
+    assert_eq!(steth_token.balance(&alice), 0);
+    assert_eq!(steth_token.balance(&contract_id), 300);
+    assert_eq!(client.try_stake_steth(&bob, &201), Err(Ok(ChrysalisError::InsufficientBalance)));
+
+    // Migrated positions are fully redeemable, staked or not
+    assert_eq!(client.preview_unstake(&300), 300);
+    client.unstake_eth(&alice, &alice, &300);
+    client.unstake_eth(&bob, &bob, &200);
+    assert_eq!(staked_token.balance(&alice), 300);
+    assert_eq!(staked_token.balance(&bob), 200);
+}
+
+#[test]
+fn test_global_totals() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &1000);
+    staked_client.mint(&bob, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 10_000, 10_000);
+    client.set_reward_rate(&2);
+
+    client.stake_eth(&alice, &600);
+    client.stake_eth(&alice, &400);
+    client.stake_eth(&bob, &1000);
+    // The pool also holds the deposit that backs the reward budget
+    assert_eq!(client.get_total_staked(), 2000);
+    assert_eq!(client.get_total_pooled(), 12_000);
+    assert_eq!(client.get_staker_count(), 2);
+
+    env.ledger().set_timestamp(100);
+    assert_eq!(client.claim(&alice, &alice), 100);
+    assert_eq!(client.claim(&bob, &bob), 100);
+    assert_eq!(client.get_total_rewards_paid(), 200);
+
+    // Claimed rewards are redeemed before staked shares, and fully exiting
+    // removes the staker
+    client.unstake_eth(&alice, &alice, &1100);
+    client.unstake_eth(&bob, &bob, &500);
+    assert_eq!(client.get_total_staked(), 600);
+    assert_eq!(client.get_total_pooled(), 10_400);
+    assert_eq!(client.get_staker_count(), 1);
+}
+
+#[test]
+fn test_solvency_and_reconcile() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let user = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&user, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 100, 50);
+    client.stake_eth(&user, &1000);
+
+    // The staked shares, unclaimed rewards and the unspent budget are all owed
+    env.ledger().set_timestamp(40);
+    let report = client.check_solvency();
+    assert_eq!(
+        report,
+        SolvencyReport {
+            staked_liability: 1100,
+            staked_balance: 1100,
+            steth_liability: 1100,
+            steth_balance: 1100,
+            solvent: true,
+        }
+    );
+    assert_eq!(client.claim(&user, &user), 80);
+    assert_eq!(client.check_solvency().steth_liability, 1020);
+    assert!(client.reconcile().solvent);
+    assert_eq!(client.get_paused(), 0);
+
+    // Shares escrowed in the withdrawal queue are owed in stETH as well; the
+    // claimed rewards are escrowed first and come back into the contract
+    client.request_unstake(&user, &200);
+    let report = client.check_solvency();
+    assert_eq!(report.steth_liability, 1100);
+    assert_eq!(report.steth_balance, 1100);
+
+    // The escrow is counted by the queue itself, not inferred from the stake records
+    env.as_contract(&contract_id, || {
+        env.storage().instance().set(&DataKey::EscrowedShares, &-1i128);
+    });
+    assert_eq!(client.try_check_solvency(), Err(Ok(ChrysalisError::InvalidAccounting)));
+    env.as_contract(&contract_id, || {
+        env.storage().instance().set(&DataKey::EscrowedShares, &200i128);
+    });
+
+    // Simulate a loss of backing
+    env.as_contract(&contract_id, || {
+        pool::write_totals(&env, 1200, pool::read_total_shares(&env));
+    });
+    let report = client.reconcile();
+    assert!(!report.solvent);
+    assert_eq!(report.staked_liability, 1200);
+    assert_eq!(
+        vec![&env, env.events().all().get(env.events().all().len() - 2).unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("deficit"),).into_val(&env),
+                (events::EVENT_VERSION, 100i128, 0i128, 40u64).into_val(&env)
+            ),
+        ]
+    );
+    assert_eq!(client.get_paused(), pause::PAUSE_ALL);
+    assert_eq!(client.try_claim(&user, &user), Err(Ok(ChrysalisError::Paused)));
+
+    // Only the admin may reconcile
+    client.reconcile();
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            admin.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract_id.clone(),
+                    Symbol::new(&env, "reconcile"),
+                    ().into_val(&env)
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+}
+
+#[test]
+fn test_fund_rewards() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let funder = soroban_sdk::Address::generate(&env);
+    let user = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&user, &1000);
+    staked_client.mint(&funder, &2000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    // Reward stETH is obtained by staking
+    client.stake_eth(&funder, &1000);
+    assert_eq!(client.try_fund_rewards(&funder, &0, &100), Err(Ok(ChrysalisError::InvalidAmount)));
+    assert_eq!(client.try_fund_rewards(&funder, &100, &0), Err(Ok(ChrysalisError::InvalidAmount)));
+    // A budget smaller than the duration would emit nothing
+    assert_eq!(client.try_fund_rewards(&funder, &99, &100), Err(Ok(ChrysalisError::InvalidAmount)));
+    assert_eq!(client.try_fund_rewards(&funder, &1001, &100), Err(Ok(ChrysalisError::InsufficientStake)));
+
+    client.fund_rewards(&funder, &1000, &100);
+    assert_eq!(steth_token.balance(&contract_id), 1000);
+    assert_eq!(client.get_reward_rate(), 10);
+    assert_eq!(client.get_period_finish(), 100);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("fund"), funder.clone()).into_val(&env),
+                (events::EVENT_VERSION, 1000i128, 10i128, 100u64, 0u64, steth_client.address.clone(), 0i128).into_val(&env)
+            ),
+        ]
+    );
+    // The funded shares left the funder's stake, so they no longer earn for them
+    assert_eq!(client.get_stake_amount(&funder), 0);
+    assert_eq!(client.get_total_staked(), 0);
+    assert_eq!(client.try_unstake_eth(&funder, &funder, &1), Err(Ok(ChrysalisError::InsufficientStake)));
+
+    // Nothing is emitted before anyone stakes
+    env.ledger().set_timestamp(20);
+    client.stake_eth(&user, &1000);
+    env.ledger().set_timestamp(60);
+    assert_eq!(client.get_reward_budget_remaining(), 600);
+
+    // Topping up rolls the unspent budget into the new period
+    client.stake_eth(&funder, &1000);
+    client.fund_rewards(&funder, &1000, &100);
+    assert_eq!(client.get_reward_rate(), 16);
+    assert_eq!(client.get_period_finish(), 160);
+
+    // Lowering the rate stretches the remaining budget
+    client.set_reward_rate(&8);
+    assert_eq!(client.get_period_finish(), 260);
+
+    // Emission stops once the budget runs out, so every claim is covered
+    env.ledger().set_timestamp(500);
+    assert_eq!(client.get_reward_budget_remaining(), 0);
+    // Emission that exhausts the budget is still counted as owed
+    env.as_contract(&contract_id, || update_reward_index(&env));
+    assert_eq!(client.check_solvency().steth_liability, 1000 + 2000);
+    assert_eq!(client.claim(&user, &user), 2000);
+    // Only the user's stake is left in custody
+    assert_eq!(steth_token.balance(&contract_id), 1000);
+    env.ledger().set_timestamp(600);
+    assert_eq!(client.vclaim(&user), 0);
+    assert_eq!(client.vclaim(&funder), 0);
+
+    // Claimed rewards are backed shares and can be redeemed with the stake
+    client.unstake_eth(&user, &user, &3000);
+    assert_eq!(staked_token.balance(&user), 3000);
+    assert_eq!(client.get_stake_amount(&user), 0);
+    assert_eq!(client.get_total_pooled(), 0);
+}
+
+#[test]
+fn test_multiple_reward_tokens() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    let (partner_token, partner_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &1000);
+    staked_client.mint(&bob, &1000);
+    partner_client.mint(&admin, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 1000, 100);
+
+    assert_eq!(client.try_add_reward_token(&steth_client.address), Err(Ok(ChrysalisError::RewardTokenExists)));
+    assert_eq!(
+        client.try_fund_reward_token(&admin, &partner_client.address, &1000, &100),
+        Err(Ok(ChrysalisError::RewardTokenNotFound))
+    );
+    client.add_reward_token(&partner_client.address);
+    assert_eq!(client.try_add_reward_token(&partner_client.address), Err(Ok(ChrysalisError::RewardTokenExists)));
+    assert_eq!(client.get_reward_tokens(), vec![&env, partner_client.address.clone()]);
+    client.fund_reward_token(&admin, &partner_client.address, &1000, &100);
+    assert_eq!(client.get_reward_pool(&partner_client.address).rate, 10);
+
+    client.stake_eth(&alice, &1000);
+    env.ledger().set_timestamp(50);
+    client.stake_eth(&bob, &1000);
+
+    // Removing the token stops its emission and refunds the rest of the budget
+    env.ledger().set_timestamp(80);
+    client.remove_reward_token(&partner_client.address);
+    assert_eq!(partner_token.balance(&admin), 200);
+    assert_eq!(
+        client.try_remove_reward_token(&partner_client.address),
+        Err(Ok(ChrysalisError::RewardTokenNotFound))
+    );
+
+    env.ledger().set_timestamp(120);
+    assert_eq!(client.pending_reward(&alice, &partner_client.address), 650);
+    assert_eq!(client.pending_reward(&bob, &partner_client.address), 150);
+    assert_eq!(client.pending_reward(&alice, &steth_client.address), 750);
+
+    // Earned rewards stay claimable after removal
+    assert_eq!(
+        client.claim_all(&alice),
+        vec![
+            &env,
+            (steth_client.address.clone(), 750i128),
+            (partner_client.address.clone(), 650i128),
+        ]
+    );
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("rwd_claim"), alice.clone()).into_val(&env),
+                (events::EVENT_VERSION, partner_client.address.clone(), 650i128, 120u64).into_val(&env)
+            ),
+        ]
+    );
+    assert_eq!(partner_token.balance(&alice), 650);
+    assert_eq!(client.pending_reward(&alice, &partner_client.address), 0);
+    assert_eq!(client.claim_all(&alice), vec![&env]);
+
+    client.unstake_eth(&bob, &bob, &1000);
+    assert_eq!(client.claim_all(&bob).get(1), Some((partner_client.address.clone(), 150i128)));
+
+    // The registry is bounded
+    for _ in 1..reward_tokens::MAX_REWARD_TOKENS {
+        client.add_reward_token(&soroban_sdk::Address::generate(&env));
+    }
+    assert_eq!(
+        client.try_add_reward_token(&soroban_sdk::Address::generate(&env)),
+        Err(Ok(ChrysalisError::TooManyRewardTokens))
+    );
+    assert_eq!(
+        client.try_pending_reward(&alice, &soroban_sdk::Address::generate(&env)),
+        Err(Ok(ChrysalisError::RewardTokenNotFound))
+    );
+}
+
+#[test]
+fn test_compound() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let funder = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&funder, &2000);
+    staked_client.mint(&alice, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    // The reward budget is funded with stETH obtained by staking
+    client.stake_eth(&funder, &2000);
+    client.stake_eth(&alice, &1000);
+    client.fund_rewards(&funder, &1000, &100);
+
+    env.ledger().set_timestamp(40);
+    assert_eq!(client.compound(&alice, &alice), 200);
+    assert_eq!(client.get_stake_amount(&alice), 1200);
+    // The restaked shares are held by the contract, not paid out
+    assert_eq!(steth_token.balance(&alice), 0);
+    // Stake records never add up to more than the shares backing them
+    assert!(client.get_total_staked() <= env.as_contract(&contract_id, || pool::read_total_shares(&env)));
+    assert_eq!(client.get_total_staked(), 2200);
+    assert_eq!(client.vclaim(&alice), 0);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("compound"), alice.clone()).into_val(&env),
+                (events::EVENT_VERSION, 200i128, 1200i128, 40u64).into_val(&env)
+            ),
+        ]
+    );
+    // Nothing left to compound
+    assert_eq!(client.compound(&alice, &alice), 0);
+
+    // Keepers only compound for users who opted in
+    client.set_auto_compound(&alice, &true);
+    assert!(client.get_auto_compound(&alice));
+    assert!(!client.get_auto_compound(&funder));
+    env.ledger().set_timestamp(62);
+    assert_eq!(client.compound_batch(&vec![&env, alice.clone(), funder.clone()]), 1);
+    assert_eq!(client.get_stake_amount(&alice), 1320);
+    assert_eq!(client.vclaim(&funder), 300);
+
+    let mut too_many = vec![&env];
+    for _ in 0..MAX_COMPOUND_BATCH + 1 {
+        too_many.push_back(soroban_sdk::Address::generate(&env));
+    }
+    assert_eq!(client.try_compound_batch(&too_many), Err(Ok(ChrysalisError::BatchTooLarge)));
+
+    client.set_auto_compound(&alice, &false);
+    env.ledger().set_timestamp(70);
+    assert_eq!(client.compound_batch(&vec![&env, alice.clone()]), 0);
+
+    assert!(client.get_total_staked() <= env.as_contract(&contract_id, || pool::read_total_shares(&env)));
+
+    // The compounded position is redeemable like any other
+    client.unstake_eth(&alice, &alice, &1320);
+    assert_eq!(staked_token.balance(&alice), 1320);
+    assert_eq!(steth_token.balance(&alice), 0);
+}
+
+#[test]
+fn test_locked_stake_boost() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &1000);
+    staked_client.mint(&bob, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 10_000, 1000);
+
+    // 1x without a lock, up to 3x for a lock of 1000 seconds
+    assert_eq!(client.try_set_boost_curve(&9_999, &1000), Err(Ok(ChrysalisError::InvalidAmount)));
+    client.set_boost_curve(&30_000, &1000);
+    assert_eq!(client.get_boost_curve(), BoostCurve { max_boost: 30_000, max_lock_duration: 1000 });
+    assert_eq!(client.try_stake_locked(&alice, &1000, &0), Err(Ok(ChrysalisError::InvalidLockDuration)));
+    assert_eq!(client.try_stake_locked(&alice, &1000, &1001), Err(Ok(ChrysalisError::InvalidLockDuration)));
+
+    let lock_id = client.stake_locked(&alice, &1000, &500);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("lock"), alice.clone()).into_val(&env),
+                (events::EVENT_VERSION, lock_id, 1000i128, 500u64, 20_000u32, 0u64).into_val(&env)
+            ),
+        ]
+    );
+    assert_eq!(
+        client.get_locks(&alice),
+        vec![&env, Lock { id: lock_id, amount: 1000, unlock_time: 500, boost: 20_000 }]
+    );
+    client.stake_eth(&bob, &1000);
+    assert_eq!(client.get_stake_amount(&alice), 1000);
+
+    // Alice earns with twice Bob's weight
+    env.ledger().set_timestamp(30);
+    assert_eq!(client.vclaim(&alice), 200);
+    assert_eq!(client.vclaim(&bob), 100);
+
+    // Locked shares cannot leave early
+    assert_eq!(client.try_unstake_eth(&alice, &alice, &1), Err(Ok(ChrysalisError::StakeLocked)));
+    assert_eq!(client.try_request_unstake(&alice, &1), Err(Ok(ChrysalisError::StakeLocked)));
+
+    // Extending recomputes the boost for the new duration
+    assert_eq!(client.try_extend_lock(&alice, &lock_id, &400), Err(Ok(ChrysalisError::InvalidLockDuration)));
+    assert_eq!(client.try_extend_lock(&alice, &(lock_id + 1), &900), Err(Ok(ChrysalisError::LockNotFound)));
+    client.extend_lock(&alice, &lock_id, &900);
+    assert_eq!(client.get_locks(&alice).get(0).unwrap().unlock_time, 930);
+    assert_eq!(client.get_locks(&alice).get(0).unwrap().boost, 28_000);
+    env.ledger().set_timestamp(68);
+    assert_eq!(client.vclaim(&alice), 480);
+    assert_eq!(client.vclaim(&bob), 200);
+
+    // Once expired, the lock is dropped and the shares can be withdrawn
+    env.ledger().set_timestamp(930);
+    client.unstake_eth(&alice, &alice, &1000);
+    assert_eq!(staked_token.balance(&alice), 1000);
+    assert_eq!(client.get_locks(&alice), vec![&env]);
+}
+
+#[test]
+fn test_expired_lock_stops_boosting() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let keeper = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &2000);
+    staked_client.mint(&bob, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    fund_rewards_by_staking(&env, &client, &staked_client, 9000, 1000);
+    client.set_boost_curve(&30_000, &1000);
+
+    let lock_id = client.stake_locked(&alice, &1000, &500);
+    client.stake_eth(&bob, &1000);
+    env.ledger().set_timestamp(500);
+    assert_eq!(client.vclaim(&alice), 3000);
+    assert_eq!(client.vclaim(&bob), 1500);
+
+    // Nothing to drop before the unlock time, then anyone can drop the lock
+    env.ledger().set_timestamp(499);
+    assert_eq!(client.kick(&alice), 0);
+    env.ledger().set_timestamp(500);
+    assert_eq!(client.kick(&keeper), 0);
+    assert_eq!(client.kick(&alice), 1);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("unlock"), alice.clone()).into_val(&env),
+                (events::EVENT_VERSION, lock_id, 1000i128, 0i128, 1000i128, 500u64).into_val(&env)
+            ),
+        ]
+    );
+    assert_eq!(client.get_locks(&alice), vec![&env]);
+    assert_eq!(env.as_contract(&contract_id, || read_total_weight(&env)), 2000);
+    assert_eq!(client.kick(&alice), 0);
+
+    // Both stakes now earn the same
+    env.ledger().set_timestamp(600);
+    assert_eq!(client.vclaim(&alice), 3000 + 450);
+    assert_eq!(client.vclaim(&bob), 1500 + 450);
+
+    // Claiming drops an expired lock as well
+    client.stake_locked(&alice, &1000, &100);
+    env.ledger().set_timestamp(700);
+    client.claim(&alice, &alice);
+    assert_eq!(client.get_locks(&alice), vec![&env]);
+    assert_eq!(env.as_contract(&contract_id, || read_total_weight(&env)), 3000);
+    env.ledger().set_timestamp(800);
+    assert_eq!(client.vclaim(&alice), 600);
+}
+
+#[test]
+fn test_early_unlock_penalty() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let treasury = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let carol = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &1000);
+    staked_client.mint(&bob, &1000);
+    staked_client.mint(&carol, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    client.set_boost_curve(&30_000, &1000);
+    fund_rewards_by_staking(&env, &client, &staked_client, 1000, 100);
+
+    let lock_id = client.stake_locked(&alice, &1000, &1000);
+    client.stake_eth(&bob, &1000);
+
+    // 800 of 1000 seconds remain, so 40% of the lock is forfeited
+    env.ledger().set_timestamp(200);
+    assert_eq!(client.early_unlock(&alice, &lock_id), 400);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("unlock"), alice.clone()).into_val(&env),
+                (events::EVENT_VERSION, lock_id, 600i128, 400i128, 600i128, 200u64).into_val(&env)
+            ),
+        ]
+    );
+    assert_eq!(client.try_early_unlock(&alice, &lock_id), Err(Ok(ChrysalisError::LockNotFound)));
+    assert_eq!(client.get_stake_amount(&alice), 600);
+    assert_eq!(client.get_locks(&alice), vec![&env]);
+
+    // Without a treasury the penalty shares join the reward budget instead of
+    // raising the value of stETH held outside stakes. The period has ended, so
+    // they wait for the next funding
+    assert_eq!(
+        vec![&env, env.events().all().get(env.events().all().len() - 2).unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("fund"), alice.clone()).into_val(&env),
+                (events::EVENT_VERSION, 400i128, 10i128, 100u64, 200u64, steth_client.address.clone(), 600i128).into_val(&env)
+            ),
+        ]
+    );
+    assert_eq!(client.get_reward_budget_remaining(), 400);
+    assert_eq!(client.get_total_pooled(), 3000);
+    assert_eq!(client.get_exchange_rate(), pool::EXCHANGE_RATE_PRECISION);
+    let report = client.check_solvency();
+    assert!(report.solvent);
+    assert_eq!(report.staked_liability, report.staked_balance);
+    assert_eq!(report.steth_liability, report.steth_balance);
+    env.ledger().set_timestamp(240);
+    assert_eq!(client.vclaim(&alice), 750);
+    assert_eq!(client.vclaim(&bob), 250);
+
+    // The next funding emits the penalty along with the new rewards
+    fund_rewards_by_staking(&env, &client, &staked_client, 1000, 100);
+    assert_eq!(client.get_reward_rate(), 14);
+    env.ledger().set_timestamp(340);
+    assert_eq!(client.vclaim(&alice), 750 + 525);
+    assert_eq!(client.vclaim(&bob), 250 + 875);
+
+    // The released shares are freely withdrawable
+    client.unstake_eth(&alice, &alice, &600);
+    assert_eq!(staked_token.balance(&alice), 600);
+    assert_eq!(steth_token.balance(&alice), 0);
+
+    // With a treasury configured, the penalty becomes the treasury's stake
+    client.set_treasury(&Some(treasury.clone()));
+    assert_eq!(client.get_treasury(), Some(treasury.clone()));
+    let lock_id = client.stake_locked(&carol, &1000, &500);
+    env.ledger().set_timestamp(590);
+    let penalty = client.early_unlock(&carol, &lock_id);
+    assert_eq!(penalty, 125);
+    assert_eq!(
+        vec![&env, env.events().all().get(env.events().all().len() - 2).unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("stake"), treasury.clone()).into_val(&env),
+                (events::EVENT_VERSION, 125i128, 125i128, 590u64, 125i128, carol.clone()).into_val(&env)
+            ),
+        ]
+    );
+    assert_eq!(client.get_stake_amount(&treasury), penalty);
+    assert!(client.check_solvency().solvent);
+    client.unstake_eth(&treasury, &treasury, &penalty);
+    assert_eq!(staked_token.balance(&treasury), 125);
+    assert!(client.check_solvency().solvent);
+
+    client.set_treasury(&None);
+    assert_eq!(client.get_treasury(), None);
+
+    // A penalty taken while a period runs raises its rate instead
+    fund_rewards_by_staking(&env, &client, &staked_client, 1000, 100);
+    staked_client.mint(&carol, &1000);
+    let lock_id = client.stake_locked(&carol, &1000, &500);
+    env.ledger().set_timestamp(640);
+    assert_eq!(client.early_unlock(&carol, &lock_id), 225);
+    assert_eq!(client.get_reward_budget_remaining(), 500 + 225);
+    assert_eq!(client.get_reward_rate(), 725 / 50);
+    assert_eq!(client.get_period_finish(), 690);
+}
+
+#[test]
+fn test_voting_power() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let carol = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &3650);
+    staked_client.mint(&bob, &3650);
+    staked_client.mint(&carol, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    // 3650 shares locked for a week carry 70 votes
+    let week = ve::WEEK;
+    let lock_id = client.stake_locked(&alice, &3650, &(10 * week));
+    client.stake_locked(&bob, &3650, &(4 * week));
+    client.stake_eth(&carol, &1000);
+    assert_eq!(client.get_voting_power(&alice), 700);
+    assert_eq!(client.get_voting_power(&bob), 280);
+    assert_eq!(client.get_voting_power(&carol), 0);
+    assert_eq!(client.get_total_voting_power(), 980);
+
+    // Power decays linearly towards the unlock time
+    env.ledger().set_timestamp(2 * week);
+    assert_eq!(client.get_voting_power(&alice), 560);
+    assert_eq!(client.get_voting_power(&bob), 140);
+    assert_eq!(client.get_total_voting_power(), 700);
+
+    client.extend_lock(&alice, &lock_id, &(20 * week));
+    assert_eq!(client.get_voting_power(&alice), 1400);
+    assert_eq!(client.get_total_voting_power(), 1540);
+
+    // Bob's lock has ended
+    env.ledger().set_timestamp(5 * week);
+    assert_eq!(client.get_voting_power(&bob), 0);
+    assert_eq!(client.get_voting_power(&alice), 1190);
+    assert_eq!(client.get_total_voting_power(), 1190);
+
+    // History and projections
+    assert_eq!(client.get_voting_power_at(&alice, &0), 700);
+    assert_eq!(client.get_voting_power_at(&alice, &week), 630);
+    assert_eq!(client.get_voting_power_at(&alice, &(2 * week)), 1400);
+    assert_eq!(client.get_voting_power_at(&alice, &(12 * week)), 700);
+    assert_eq!(client.get_voting_power_at(&alice, &(30 * week)), 0);
+    assert_eq!(client.get_voting_power_at(&carol, &week), 0);
+
+    client.early_unlock(&alice, &lock_id);
+    assert_eq!(client.get_voting_power(&alice), 0);
+    assert_eq!(client.get_total_voting_power(), 0);
+    assert_eq!(client.get_voting_power_at(&alice, &(4 * week)), 1260);
+}
+
+#[test]
+fn test_stake_checkpoints() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &1000);
+    staked_client.mint(&bob, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    env.ledger().set_sequence_number(10);
+    client.stake_eth(&alice, &500);
+    // Several changes in one ledger leave a single checkpoint
+    client.stake_eth(&alice, &100);
+    env.ledger().set_sequence_number(20);
+    client.stake_eth(&bob, &1000);
+    env.ledger().set_sequence_number(30);
+    client.unstake_eth(&alice, &alice, &200);
+    env.ledger().set_sequence_number(40);
+    client.stake_eth(&alice, &400);
+
+    assert_eq!(client.get_stake_at(&alice, &9), 0);
+    assert_eq!(client.get_stake_at(&alice, &10), 600);
+    assert_eq!(client.get_stake_at(&alice, &25), 600);
+    assert_eq!(client.get_stake_at(&alice, &30), 400);
+    assert_eq!(client.get_stake_at(&alice, &39), 400);
+    assert_eq!(client.get_stake_at(&alice, &40), 800);
+    assert_eq!(client.get_stake_at(&alice, &1000), 800);
+    assert_eq!(client.get_stake_at(&bob, &19), 0);
+    assert_eq!(client.get_stake_at(&bob, &20), 1000);
+
+    assert_eq!(client.get_total_staked_at(&9), 0);
+    assert_eq!(client.get_total_staked_at(&10), 600);
+    assert_eq!(client.get_total_staked_at(&20), 1600);
+    assert_eq!(client.get_total_staked_at(&35), 1400);
+    assert_eq!(client.get_total_staked_at(&40), 1800);
+    env.as_contract(&contract_id, || {
+        let count: u32 = env.storage().persistent().get(&DataKey::StakeCheckpointCount(alice.clone())).unwrap();
+        assert_eq!(count, 3);
+    });
+
+    // History stays readable long after the default month, and reading it keeps it alive
+    for _ in 0..6 {
+        env.as_contract(&contract_id, || storage::extend_instance(&env));
+        env.ledger().with_mut(|li| li.sequence_number += 6 * 17280);
+    }
+    assert_eq!(client.get_stake_at(&alice, &25), 600);
+    assert_eq!(client.get_total_staked_at(&35), 1400);
+    let ttl = env.as_contract(&contract_id, || {
+        env.storage().persistent().get_ttl(&DataKey::StakeCheckpoint(alice.clone(), 0))
+    });
+    assert_eq!(ttl, storage::HISTORY_BUMP_AMOUNT);
+}
+
+#[test]
+fn test_delegation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let delegatee = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &3650);
+    staked_client.mint(&bob, &4650);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    let week = ve::WEEK;
+    let lock_id = client.stake_locked(&alice, &3650, &(10 * week));
+    client.stake_locked(&bob, &3650, &(4 * week));
+    assert_eq!(client.get_delegate(&alice), None);
+    assert_eq!(client.get_delegated_power(&delegatee), 0);
+
+    client.delegate(&alice, &delegatee);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("delegate"), alice.clone()).into_val(&env),
+                (events::EVENT_VERSION, None::<Address>, delegatee.clone(), 0u64).into_val(&env)
+            ),
+        ]
+    );
+    client.delegate(&bob, &delegatee);
+    assert_eq!(client.get_delegate(&alice), Some(delegatee.clone()));
+    assert_eq!(client.get_delegated_power(&delegatee), 980);
+    assert_eq!(client.get_delegated_power(&delegatee), client.get_total_voting_power());
+
+    // The power moves to the delegatee instead of being counted twice
+    assert_eq!(client.get_voting_power(&alice), 0);
+    assert_eq!(client.get_voting_power(&bob), 0);
+
+    // Unlocked stake carries no voting power, delegated or not
+    client.stake_eth(&bob, &1000);
+    assert_eq!(client.get_delegated_power(&delegatee), 980);
+
+    // Delegated power decays with the locks and follows changes to them
+    env.ledger().set_timestamp(2 * week);
+    assert_eq!(client.get_delegated_power(&delegatee), 700);
+    client.extend_lock(&alice, &lock_id, &(20 * week));
+    assert_eq!(
+        vec![&env, env.events().all().get(env.events().all().len() - 2).unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("dlg_power"), delegatee.clone()).into_val(&env),
+                (events::EVENT_VERSION, 700i128, 1540i128, 2 * week).into_val(&env)
+            ),
+        ]
+    );
+
+    // Bob's lock has ended
+    env.ledger().set_timestamp(5 * week);
+    assert_eq!(client.get_delegated_power(&delegatee), 1190);
+    assert_eq!(client.get_voting_power(&alice), 0);
+    assert_eq!(client.get_delegated_power_at(&delegatee, &0), 980);
+    assert_eq!(client.get_delegated_power_at(&delegatee, &week), 840);
+    assert_eq!(client.get_delegated_power_at(&delegatee, &(12 * week)), 700);
+
+    // Redelegating moves the remaining power
+    client.delegate(&alice, &alice);
+    assert_eq!(client.get_delegated_power(&delegatee), 0);
+    assert_eq!(client.get_delegated_power(&alice), 1190);
+    assert_eq!(client.get_delegated_power_at(&delegatee, &(4 * week)), 1260);
+
+    // Delegating to herself gives Alice her own power back, for the future only
+    assert_eq!(client.get_voting_power(&alice), 1190);
+    assert_eq!(client.get_voting_power_at(&alice, &(4 * week)), 0);
+    client.early_unlock(&alice, &lock_id);
+    assert_eq!(client.get_delegated_power(&alice), 0);
+    assert_eq!(client.get_voting_power(&alice), 0);
+}
+
+#[test]
+fn test_stake_for() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let payer = soroban_sdk::Address::generate(&env);
+    let beneficiary = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&payer, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    assert_eq!(
+        client.try_stake_for(&payer, &beneficiary, &1001),
+        Err(Ok(ChrysalisError::InsufficientBalance))
+    );
+    client.stake_for(&payer, &beneficiary, &600);
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            payer.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract_id.clone(),
+                    Symbol::new(&env, "stake_for"),
+                    (payer.clone(), beneficiary.clone(), 600i128).into_val(&env)
+                )),
+                sub_invocations: std::vec![AuthorizedInvocation {
+                    function: AuthorizedFunction::Contract((
+                        staked_client.address.clone(),
+                        Symbol::new(&env, "transfer"),
+                        (payer.clone(), contract_id.clone(), 600i128).into_val(&env)
+                    )),
+                    sub_invocations: std::vec![]
+                }]
+            }
+        )]
+    );
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("stake"), beneficiary.clone()).into_val(&env),
+                (events::EVENT_VERSION, 600i128, 600i128, 0u64, 600i128, payer.clone()).into_val(&env)
+            ),
+        ]
+    );
+
+    // The position belongs to the beneficiary, and the stETH backing it is in custody
+    assert_eq!(staked_token.balance(&payer), 400);
+    assert_eq!(client.get_stake_amount(&beneficiary), 600);
+    assert_eq!(client.get_stake_amount(&payer), 0);
+    assert_eq!(steth_token.balance(&beneficiary), 0);
+    assert_eq!(steth_token.balance(&contract_id), 600);
+    client.unstake_eth(&beneficiary, &beneficiary, &600);
+    assert_eq!(staked_token.balance(&beneficiary), 600);
+}
+
+#[test]
+fn test_operator_approvals() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let funder = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let keeper = soroban_sdk::Address::generate(&env);
+    let vault = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&funder, &2000);
+    staked_client.mint(&alice, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    client.stake_eth(&funder, &2000);
+    client.stake_eth(&alice, &1000);
+    client.fund_rewards(&funder, &1000, &100);
+
+    assert_eq!(client.try_claim(&keeper, &alice), Err(Ok(ChrysalisError::Unauthorized)));
+    assert_eq!(
+        client.try_approve_operator(&alice, &keeper, &8, &100, &0),
+        Err(Ok(ChrysalisError::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_approve_operator(&alice, &keeper, &operator::OPERATOR_UNSTAKE, &100, &0),
+        Err(Ok(ChrysalisError::InvalidAmount))
+    );
+
+    let permissions = operator::OPERATOR_CLAIM | operator::OPERATOR_UNSTAKE;
+    client.approve_operator(&alice, &keeper, &permissions, &100, &500);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("operator"), alice.clone(), keeper.clone()).into_val(&env),
+                (events::EVENT_VERSION, permissions, 100u32, 0u64).into_val(&env)
+            ),
+        ]
+    );
+    assert!(client.is_operator(&alice, &keeper, &permissions));
+    assert!(!client.is_operator(&alice, &keeper, &operator::OPERATOR_COMPOUND));
+
+    // Each operator has an allowance of its own
+    client.approve_operator(&alice, &vault, &operator::OPERATOR_UNSTAKE, &50, &200);
+
+    // Payouts go to the staker, and only the operator signs
+    env.ledger().set_timestamp(40);
+    assert_eq!(client.try_compound(&keeper, &alice), Err(Ok(ChrysalisError::Unauthorized)));
+    assert_eq!(client.claim(&keeper, &alice), 200);
+    assert_eq!(steth_token.balance(&alice), 200);
+    assert_eq!(steth_token.balance(&keeper), 0);
+
+    client.unstake_eth(&keeper, &alice, &400);
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            keeper.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract_id.clone(),
+                    Symbol::new(&env, "unstake_eth"),
+                    (keeper.clone(), alice.clone(), 400i128).into_val(&env)
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    // Operators only redeem from the stake, never the stETH the staker holds
+    assert_eq!(client.get_stake_amount(&alice), 600);
+    assert_eq!(steth_token.balance(&alice), 200);
+    assert_eq!(staked_token.balance(&alice), 400);
+    assert_eq!(staked_token.balance(&keeper), 0);
+    assert_eq!(
+        client.try_unstake_eth(&keeper, &alice, &200),
+        Err(Ok(ChrysalisError::AllowanceExceeded))
+    );
+
+    // Revoking takes back what the operator had left
+    client.revoke_operator(&alice, &keeper);
+    assert!(!client.is_operator(&alice, &keeper, &operator::OPERATOR_CLAIM));
+    assert_eq!(client.try_unstake_eth(&keeper, &alice, &100), Err(Ok(ChrysalisError::Unauthorized)));
+
+    // Approvals lapse after their expiration ledger
+    env.ledger().set_sequence_number(51);
+    assert!(!client.is_operator(&alice, &vault, &operator::OPERATOR_UNSTAKE));
+    assert_eq!(client.try_unstake_eth(&vault, &alice, &100), Err(Ok(ChrysalisError::Unauthorized)));
+    assert_eq!(
+        client.try_approve_operator(&alice, &vault, &operator::OPERATOR_UNSTAKE, &50, &200),
+        Err(Ok(ChrysalisError::InvalidExpirationLedger))
+    );
+
+    client.approve_operator(&alice, &keeper, &operator::OPERATOR_COMPOUND, &200, &0);
+    assert!(client.is_operator(&alice, &keeper, &operator::OPERATOR_COMPOUND));
+    client.revoke_operator(&alice, &keeper);
+    assert!(!client.is_operator(&alice, &keeper, &operator::OPERATOR_COMPOUND));
+    assert_eq!(client.try_compound(&keeper, &alice), Err(Ok(ChrysalisError::Unauthorized)));
+}
+
+#[test]
+fn test_transfer_stake() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let funder = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (_, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&funder, &2000);
+    staked_client.mint(&alice, &1500);
+    staked_client.mint(&bob, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    client.stake_eth(&funder, &2000);
+    client.stake_eth(&alice, &1000);
+    client.fund_rewards(&funder, &1000, &100);
+    env.ledger().set_timestamp(20);
+    client.stake_eth(&bob, &1000);
+
+    env.ledger().set_timestamp(40);
+    assert_eq!(client.try_transfer_stake(&alice, &alice, &500), Err(Ok(ChrysalisError::InvalidAmount)));
+    assert_eq!(client.try_transfer_stake(&alice, &bob, &1001), Err(Ok(ChrysalisError::InsufficientStake)));
+    assert_eq!(client.vclaim(&alice), 166);
+    assert_eq!(client.vclaim(&bob), 66);
+
+    // Half of the stake moves with half of its unclaimed rewards, and merges into bob's
+    client.transfer_stake(&alice, &bob, &500);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("transfer"), alice.clone(), bob.clone()).into_val(&env),
+                (events::EVENT_VERSION, 500i128, 83i128, 500i128, 1500i128, 40u64).into_val(&env)
+            ),
+        ]
+    );
+    assert_eq!(client.vclaim(&alice), 83);
+    assert_eq!(client.vclaim(&bob), 149);
+    assert_eq!(client.get_stake_amount(&alice), 500);
+    assert_eq!(client.get_stake_amount(&bob), 1500);
+    // The stETH backing both positions stays in the contract
+    assert_eq!(steth_token.balance(&alice), 0);
+    assert_eq!(steth_token.balance(&bob), 0);
+    assert_eq!(client.get_total_staked(), 3000);
+    let merged = env.as_contract(&contract_id, || storage::read_stake(&env, &bob));
+    assert_eq!(merged.timestamp, 13);
+
+    // Both positions accrue on their new weights
+    env.ledger().set_timestamp(70);
+    assert_eq!(client.vclaim(&alice), 83 + 50);
+    assert_eq!(client.vclaim(&bob), 149 + 150);
+
+    // Locked shares stay behind
+    client.stake_locked(&alice, &500, &1000);
+    assert_eq!(client.try_transfer_stake(&alice, &bob, &600), Err(Ok(ChrysalisError::StakeLocked)));
+    client.transfer_stake(&alice, &bob, &500);
+    assert_eq!(client.get_stake_amount(&alice), 500);
+    assert_eq!(client.get_locks(&alice).len(), 1);
+    assert_eq!(client.get_locks(&bob).len(), 0);
+}
+
+#[test]
+fn test_staked_shares_cannot_leave_custody() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let alt = soroban_sdk::Address::generate(&env);
+    let (staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    client.stake_locked(&alice, &1000, &(300 * 24 * 60 * 60));
+
+    // Alice holds no stETH to move to a second address and redeem there
+    assert!(steth_token.try_transfer(&alice, &alt, &1000).is_err());
+    assert_eq!(client.try_unstake_eth(&alt, &alt, &1000), Err(Ok(ChrysalisError::InsufficientStake)));
+
+    // The locked stake stays fully backed
+    assert_eq!(client.get_stake_amount(&alice), 1000);
+    assert_eq!(steth_token.balance(&contract_id), 1000);
+    assert_eq!(staked_token.balance(&contract_id), 1000);
+    assert!(client.check_solvency().solvent);
+}
+
+#[test]
+fn test_transfer_stake_boosted_sender() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let funder = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let bob = soroban_sdk::Address::generate(&env);
+    let (_, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&funder, &3000);
+    staked_client.mint(&alice, &1000);
+    staked_client.mint(&bob, &2000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+    client.set_boost_curve(&30_000, &1000);
+    client.stake_eth(&funder, &3000);
+    client.stake_eth(&alice, &500);
+    client.stake_locked(&alice, &500, &1000);
+    client.stake_eth(&bob, &2000);
+    client.fund_rewards(&funder, &1000, &100);
+
+    // Alice's 500 unlocked shares earned a quarter of her weight of 2000
+    env.ledger().set_timestamp(50);
+    assert_eq!(client.vclaim(&alice), 166);
+    client.transfer_stake(&alice, &bob, &500);
+    assert_eq!(
+        vec![&env, env.events().all().last().unwrap()],
+        vec![
+            &env,
+            (
+                contract_id.clone(),
+                (symbol_short!("transfer"), alice.clone(), bob.clone()).into_val(&env),
+                (events::EVENT_VERSION, 500i128, 41i128, 500i128, 2500i128, 50u64).into_val(&env)
+            ),
+        ]
+    );
+    assert_eq!(client.vclaim(&alice), 125);
+    assert_eq!(client.vclaim(&bob), 166 + 41);
+}
+
+#[test]
+fn test_lock_entries_outlive_idle_month() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = soroban_sdk::Address::generate(&env);
+    let alice = soroban_sdk::Address::generate(&env);
+    let (_staked_token, staked_client) = create_token_contract(&env, &admin);
+    let (_steth_token, steth_client) = create_token_contract(&env, &admin);
+    staked_client.mint(&alice, &1000);
+
+    let contract_id = env.register_contract(None, ChrysalisContract);
+    steth_client.set_admin(&contract_id);
+    let client = ChrysalisContractClient::new(&env, &contract_id);
+    client.initialize_contract(&admin, &staked_client.address, &steth_client.address);
+
+    let day: u64 = 24 * 60 * 60;
+    client.stake_locked(&alice, &1000, &(200 * day));
+    let end = client.get_locks(&alice).get(0).unwrap().unlock_time / ve::WEEK * ve::WEEK;
+    let keys = [
+        DataKey::Locks(alice.clone()),
+        DataKey::UserSlopeChange(alice.clone(), end),
+        DataKey::SlopeChange(end),
+        DataKey::UserVePoint(alice.clone(), 0),
+    ];
+    let ttls = |env: &Env| -> std::vec::Vec<u32> {
+        env.as_contract(&contract_id, || {
+            keys.iter().map(|key| env.storage().persistent().get_ttl(key)).collect()
+        })
+    };
+    // Lock entries are kept until the lock ends, not just for the default month
+    for ttl in ttls(&env) {
+        assert!(ttl > storage::PERSISTENT_BUMP_AMOUNT);
+    }
+
+    // A keeper bumping the stake over an idle month keeps the lock alive with it
+    env.as_contract(&contract_id, || env.storage().instance().extend_ttl(40 * 17280, 40 * 17280));
+    for _ in 0..2 {
+        env.ledger().with_mut(|li| {
+            li.sequence_number += 20 * 17280;
+            li.timestamp += 20 * day;
+        });
+        client.bump_stake(&alice);
+    }
+    for ttl in ttls(&env) {
+        assert!(ttl as u64 >= (end - 40 * day) / 5);
+    }
+    assert_eq!(client.get_locks(&alice).len(), 1);
+    assert!(client.get_voting_power(&alice) > 0);
+}
@@ -0,0 +1,312 @@
+//! Vote-escrowed voting power.
+//!
+//! Locked shares carry voting power that decays linearly to zero at the end of
+//! the lock: a share locked for `VE_MAX_TIME` counts as one vote, a share with
+//! half that time left counts as half a vote. Unlocked stakes carry none.
+//!
+//! As in Curve's VotingEscrow, power is tracked as a bias and a slope. Lock ends
+//! are rounded down to whole weeks, and the slope each week sheds as locks end
+//! is kept in a slope change entry for that week. Every change to a user's
+//! locks appends a point to the global history and to the history the power
+//! of those locks is counted in, so power can be evaluated at any past or
+//! future timestamp by finding the latest point before it and walking the
+//! weekly slope changes from there.
+//!
+//! Power delegated to a delegatee (see the `delegation` module) has a history
+//! of its own, with its own slope changes. Delegating adds the bias and slope
+//! of the delegator's locks to it, and every later change to those locks is
+//! applied to it as well. While the locks are delegated to another address
+//! they are taken out of the user's own history, so their power is only ever
+//! counted once.
+//!
+//! Biases are kept in share-seconds; they are divided by `VE_MAX_TIME` only
+//! when power is read.
+
+use soroban_sdk::{contracttype, Address, Env, Vec};
+
+use crate::lock::Lock;
+use crate::storage;
+use crate::DataKey;
+
+pub(crate) const WEEK: u64 = 7 * 24 * 60 * 60;
+/// Lock duration at which one share carries one vote.
+pub(crate) const VE_MAX_TIME: u64 = 365 * 24 * 60 * 60;
+
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VePoint {
+    /// Voting power at `ts`, in share-seconds.
+    pub bias: i128,
+    /// Shares whose power is decaying at `ts`.
+    pub slope: i128,
+    pub ts: u64,
+}
+
+/// Timestamp at which a lock's voting power reaches zero.
+fn ve_end(lock: &Lock) -> u64 {
+    lock.unlock_time / WEEK * WEEK
+}
+
+fn point_from_locks(locks: &Vec<Lock>, ts: u64) -> VePoint {
+    let mut point = VePoint { ts, ..Default::default() };
+    for lock in locks.iter() {
+        let end = ve_end(&lock);
+        if end > ts {
+            point.bias += lock.amount * (end - ts) as i128;
+            point.slope += lock.amount;
+        }
+    }
+    point
+}
+
+fn read_slope_change(env: &Env, key: &DataKey) -> i128 {
+    env.storage().persistent().get(key).unwrap_or(0)
+}
+
+/// Adds `delta` to the slope change of week `end`, which is kept until that week.
+fn add_slope_change(env: &Env, key: DataKey, end: u64, delta: i128) {
+    let change = read_slope_change(env, &key) + delta;
+    if change == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, &change);
+        storage::extend_persistent_until(env, &key, end);
+    }
+}
+
+/// Decays `point` up to `ts`, applying the slope changes of every week boundary
+/// crossed on the way. `slope_change` maps a week to its slope change key.
+fn advance(env: &Env, mut point: VePoint, ts: u64, slope_change: impl Fn(u64) -> DataKey) -> VePoint {
+    while point.ts < ts {
+        // Nothing decays any more once every lock has ended
+        if point.slope == 0 {
+            point.ts = ts;
+            break;
+        }
+        let next_week = (point.ts / WEEK + 1) * WEEK;
+        let next = next_week.min(ts);
+        point.bias -= point.slope * (next - point.ts) as i128;
+        point.ts = next;
+        if next == next_week {
+            point.slope -= read_slope_change(env, &slope_change(next_week));
+        }
+    }
+    point.bias = point.bias.max(0);
+    point
+}
+
+fn read_point(env: &Env, key: &DataKey) -> VePoint {
+    env.storage().persistent().get(key).unwrap_or_default()
+}
+
+/// Appends `point` to a history of `count` points, replacing the last one if it
+/// has the same timestamp.
+fn push_point(env: &Env, count_key: DataKey, point_key: impl Fn(u32) -> DataKey, point: &VePoint) {
+    let mut count: u32 = env.storage().persistent().get(&count_key).unwrap_or(0);
+    if count == 0 || read_point(env, &point_key(count - 1)).ts < point.ts {
+        count += 1;
+        env.storage().persistent().set(&count_key, &count);
+    }
+    storage::extend_persistent(env, &count_key);
+    let key = point_key(count - 1);
+    env.storage().persistent().set(&key, point);
+    storage::extend_persistent(env, &key);
+}
+
+/// Returns the latest point of a history of `count` points taken at or before `ts`.
+fn find_point(env: &Env, count_key: DataKey, point_key: impl Fn(u32) -> DataKey, ts: u64) -> Option<VePoint> {
+    let count: u32 = env.storage().persistent().get(&count_key).unwrap_or(0);
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = (low + high) / 2;
+        if read_point(env, &point_key(mid)).ts <= ts {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    if low == 0 {
+        None
+    } else {
+        Some(read_point(env, &point_key(low - 1)))
+    }
+}
+
+/// Returns the last point of a history, or an empty point at the current time.
+fn last_point(env: &Env, count_key: DataKey, point_key: impl Fn(u32) -> DataKey) -> VePoint {
+    let count: u32 = env.storage().persistent().get(&count_key).unwrap_or(0);
+    if count == 0 {
+        return VePoint { ts: env.ledger().timestamp(), ..Default::default() };
+    }
+    read_point(env, &point_key(count - 1))
+}
+
+/// Records in the global history that one user's locks changed from
+/// `old_locks` to `new_locks`.
+pub(crate) fn checkpoint_total(env: &Env, old_locks: &Vec<Lock>, new_locks: &Vec<Lock>) {
+    let now = env.ledger().timestamp();
+    let old_point = point_from_locks(old_locks, now);
+    let new_point = point_from_locks(new_locks, now);
+
+    // Move the slope of every lock to the week it now ends in
+    for (locks, sign) in [(old_locks, -1), (new_locks, 1)] {
+        for lock in locks.iter() {
+            let end = ve_end(&lock);
+            if end > now {
+                add_slope_change(env, DataKey::SlopeChange(end), end, sign * lock.amount);
+            }
+        }
+    }
+
+    let last = last_point(env, DataKey::VePointCount, DataKey::VePoint);
+    let mut global = advance(env, last, now, DataKey::SlopeChange);
+    global.bias += new_point.bias - old_point.bias;
+    global.slope += new_point.slope - old_point.slope;
+    push_point(env, DataKey::VePointCount, DataKey::VePoint, &global);
+}
+
+/// Records that the locks counted in the user's own power changed from
+/// `old_locks` to `new_locks`. Taking the locks out of the user's power and
+/// back into it are changes to and from no locks.
+pub(crate) fn checkpoint_user(env: &Env, user: &Address, old_locks: &Vec<Lock>, new_locks: &Vec<Lock>) {
+    let now = env.ledger().timestamp();
+    for (locks, sign) in [(old_locks, -1), (new_locks, 1)] {
+        for lock in locks.iter() {
+            let end = ve_end(&lock);
+            if end > now {
+                add_slope_change(env, DataKey::UserSlopeChange(user.clone(), end), end, sign * lock.amount);
+            }
+        }
+    }
+
+    push_point(
+        env,
+        DataKey::UserVePointCount(user.clone()),
+        |index| DataKey::UserVePoint(user.clone(), index),
+        &point_from_locks(new_locks, now),
+    );
+    bump_user(env, user, new_locks);
+}
+
+/// Extends the entries the user's voting power is read from, and the slope
+/// changes of their locks, until the end of each lock.
+pub(crate) fn bump_user(env: &Env, user: &Address, locks: &Vec<Lock>) {
+    let now = env.ledger().timestamp();
+    let mut last_end = now;
+    for lock in locks.iter() {
+        let end = ve_end(&lock);
+        if end <= now {
+            continue;
+        }
+        last_end = last_end.max(end);
+        for key in [DataKey::UserSlopeChange(user.clone(), end), DataKey::SlopeChange(end)] {
+            if env.storage().persistent().has(&key) {
+                storage::extend_persistent_until(env, &key, end);
+            }
+        }
+    }
+
+    extend_last_point(
+        env,
+        DataKey::UserVePointCount(user.clone()),
+        |index| DataKey::UserVePoint(user.clone(), index),
+        last_end,
+    );
+}
+
+/// Extends the count and last point of a history until `until`.
+fn extend_last_point(env: &Env, count_key: DataKey, point_key: impl Fn(u32) -> DataKey, until: u64) {
+    let count: u32 = env.storage().persistent().get(&count_key).unwrap_or(0);
+    if count > 0 {
+        storage::extend_persistent_until(env, &count_key, until);
+        storage::extend_persistent_until(env, &point_key(count - 1), until);
+    }
+}
+
+/// Records that the locks of one of the delegatee's delegators changed from
+/// `old_locks` to `new_locks`. Delegating and undelegating are changes from and
+/// to no locks.
+pub(crate) fn checkpoint_delegated(env: &Env, delegatee: &Address, old_locks: &Vec<Lock>, new_locks: &Vec<Lock>) {
+    let now = env.ledger().timestamp();
+    let old_point = point_from_locks(old_locks, now);
+    let new_point = point_from_locks(new_locks, now);
+
+    for (locks, sign) in [(old_locks, -1), (new_locks, 1)] {
+        for lock in locks.iter() {
+            let end = ve_end(&lock);
+            if end > now {
+                let key = DataKey::DelegatedSlopeChange(delegatee.clone(), end);
+                add_slope_change(env, key, end, sign * lock.amount);
+            }
+        }
+    }
+
+    let count_key = DataKey::DelegatedVePointCount(delegatee.clone());
+    let point_key = |index| DataKey::DelegatedVePoint(delegatee.clone(), index);
+    let last = last_point(env, count_key.clone(), point_key);
+    let mut point = advance(env, last, now, |week| DataKey::DelegatedSlopeChange(delegatee.clone(), week));
+    point.bias += new_point.bias - old_point.bias;
+    point.slope += new_point.slope - old_point.slope;
+    push_point(env, count_key, point_key, &point);
+    bump_delegated(env, delegatee, new_locks);
+}
+
+/// Extends the entries the delegatee's power is read from, and the slope
+/// changes of a delegator's locks, until the end of each lock.
+pub(crate) fn bump_delegated(env: &Env, delegatee: &Address, locks: &Vec<Lock>) {
+    let now = env.ledger().timestamp();
+    let mut last_end = now;
+    for lock in locks.iter() {
+        let end = ve_end(&lock);
+        if end <= now {
+            continue;
+        }
+        last_end = last_end.max(end);
+        let key = DataKey::DelegatedSlopeChange(delegatee.clone(), end);
+        if env.storage().persistent().has(&key) {
+            storage::extend_persistent_until(env, &key, end);
+        }
+    }
+    extend_last_point(
+        env,
+        DataKey::DelegatedVePointCount(delegatee.clone()),
+        |index| DataKey::DelegatedVePoint(delegatee.clone(), index),
+        last_end,
+    );
+}
+
+/// Returns the user's voting power at `ts`.
+pub(crate) fn voting_power_at(env: &Env, user: &Address, ts: u64) -> i128 {
+    let Some(point) = find_point(
+        env,
+        DataKey::UserVePointCount(user.clone()),
+        |index| DataKey::UserVePoint(user.clone(), index),
+        ts,
+    ) else {
+        return 0;
+    };
+    let point = advance(env, point, ts, |week| DataKey::UserSlopeChange(user.clone(), week));
+    point.bias / VE_MAX_TIME as i128
+}
+
+/// Returns the voting power of all users together at `ts`.
+pub(crate) fn total_voting_power_at(env: &Env, ts: u64) -> i128 {
+    let Some(point) = find_point(env, DataKey::VePointCount, DataKey::VePoint, ts) else {
+        return 0;
+    };
+    advance(env, point, ts, DataKey::SlopeChange).bias / VE_MAX_TIME as i128
+}
+
+/// Returns the voting power delegated to `delegatee` at `ts`.
+pub(crate) fn delegated_power_at(env: &Env, delegatee: &Address, ts: u64) -> i128 {
+    let Some(point) = find_point(
+        env,
+        DataKey::DelegatedVePointCount(delegatee.clone()),
+        |index| DataKey::DelegatedVePoint(delegatee.clone(), index),
+        ts,
+    ) else {
+        return 0;
+    };
+    let point = advance(env, point, ts, |week| DataKey::DelegatedSlopeChange(delegatee.clone(), week));
+    point.bias / VE_MAX_TIME as i128
+}
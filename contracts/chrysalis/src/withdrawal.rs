@@ -0,0 +1,122 @@
+//! Withdrawal queue.
+//!
+//! While an unbonding period is configured, stakers leave through
+//! `request_unstake`, which stops their stake from earning rewards and escrows
+//! their shares in the contract. Once the request matures, `claim_withdrawal`
+//! burns the escrowed shares and pays out the staked token at the exchange rate
+//! of that moment. Each request lives in its own persistent entry; a user's
+//! pending request ids are kept in a bounded list next to their stake.
+
+use soroban_sdk::{contracttype, Address, Env, Vec};
+
+use crate::error::ChrysalisError;
+use crate::storage;
+use crate::DataKey;
+
+/// Maximum number of requests a single user may have pending at once.
+pub(crate) const MAX_PENDING_WITHDRAWALS: u32 = 20;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WithdrawalRequest {
+    pub id: u64,
+    pub owner: Address,
+    /// stETH shares escrowed by the request.
+    pub shares: i128,
+    /// Part of `shares` taken out of the owner's stake; the rest is stETH they
+    /// held outside it. Cancelling restores only this part to the stake.
+    pub stake_shares: i128,
+    /// Ledger timestamp from which the request can be claimed.
+    pub unlock_time: u64,
+}
+
+pub(crate) fn read_unbonding_period(env: &Env) -> u64 {
+    env.storage().instance().get(&DataKey::UnbondingPeriod).unwrap_or(0)
+}
+
+pub(crate) fn write_unbonding_period(env: &Env, period: u64) {
+    env.storage().instance().set(&DataKey::UnbondingPeriod, &period);
+}
+
+/// stETH shares held in escrow by pending requests.
+pub(crate) fn read_escrowed_shares(env: &Env) -> i128 {
+    env.storage().instance().get(&DataKey::EscrowedShares).unwrap_or(0)
+}
+
+fn add_escrowed_shares(env: &Env, delta: i128) {
+    env.storage()
+        .instance()
+        .set(&DataKey::EscrowedShares, &(read_escrowed_shares(env) + delta));
+}
+
+pub(crate) fn read_request(env: &Env, id: u64) -> Result<WithdrawalRequest, ChrysalisError> {
+    let key = DataKey::Withdrawal(id);
+    let request = env
+        .storage()
+        .persistent()
+        .get(&key)
+        .ok_or(ChrysalisError::WithdrawalNotFound)?;
+    storage::extend_persistent(env, &key);
+    Ok(request)
+}
+
+pub(crate) fn read_user_requests(env: &Env, user: &Address) -> Vec<u64> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::UserWithdrawals(user.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Stores a new request and returns it.
+pub(crate) fn create_request(
+    env: &Env,
+    owner: &Address,
+    shares: i128,
+    stake_shares: i128,
+) -> Result<WithdrawalRequest, ChrysalisError> {
+    let mut ids = read_user_requests(env, owner);
+    if ids.len() >= MAX_PENDING_WITHDRAWALS {
+        return Err(ChrysalisError::TooManyWithdrawals);
+    }
+
+    let id: u64 = env.storage().instance().get(&DataKey::NextWithdrawalId).unwrap_or(0);
+    env.storage().instance().set(&DataKey::NextWithdrawalId, &(id + 1));
+
+    let request = WithdrawalRequest {
+        id,
+        owner: owner.clone(),
+        shares,
+        stake_shares,
+        unlock_time: env.ledger().timestamp() + read_unbonding_period(env),
+    };
+    let key = DataKey::Withdrawal(id);
+    env.storage().persistent().set(&key, &request);
+    storage::extend_persistent(env, &key);
+
+    ids.push_back(id);
+    write_user_requests(env, owner, &ids);
+    add_escrowed_shares(env, shares);
+    Ok(request)
+}
+
+/// Deletes a request that has been claimed or cancelled.
+pub(crate) fn remove_request(env: &Env, request: &WithdrawalRequest) {
+    env.storage().persistent().remove(&DataKey::Withdrawal(request.id));
+
+    let mut ids = read_user_requests(env, &request.owner);
+    if let Some(index) = ids.first_index_of(request.id) {
+        ids.remove(index);
+    }
+    write_user_requests(env, &request.owner, &ids);
+    add_escrowed_shares(env, -request.shares);
+}
+
+fn write_user_requests(env: &Env, user: &Address, ids: &Vec<u64>) {
+    let key = DataKey::UserWithdrawals(user.clone());
+    if ids.is_empty() {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, ids);
+        storage::extend_persistent(env, &key);
+    }
+}